@@ -0,0 +1,128 @@
+use crate::{
+    framebuffer::Framebuffer, hittable::HitRecord, output::OutputFormat, ray::Ray, tile::Tile,
+    tonemap::DisplayTransform,
+};
+use anyhow::Result;
+use glam::{Vec2, Vec3A};
+use std::path::{Path, PathBuf};
+
+/// Surface seen by a camera ray at its first intersection. Rays that escape the scene leave
+/// every field at zero.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct AovSample {
+    pub albedo: Vec3A,
+    pub normal: Vec3A,
+    /// Distance along the camera ray.
+    pub depth: f32,
+    pub position: Vec3A,
+    pub uv: Vec2,
+    pub object_id: u32,
+}
+
+impl AovSample {
+    pub fn new(ray: &Ray, hit: &HitRecord<'_>) -> Self {
+        Self {
+            albedo: hit.material.albedo(hit),
+            normal: hit.normal,
+            depth: hit.t * ray.direction.length(),
+            position: hit.point,
+            uv: Vec2::new(hit.u, hit.v),
+            object_id: hit.object_id,
+        }
+    }
+
+    /// Adds `sample` to a per-pixel sum. Object IDs cannot be averaged, so the pixel keeps the
+    /// one of its first sample.
+    pub fn accumulate(&mut self, sample: &AovSample, first: bool) {
+        self.albedo += sample.albedo;
+        self.normal += sample.normal;
+        self.depth += sample.depth;
+        self.position += sample.position;
+        self.uv += sample.uv;
+        if first {
+            self.object_id = sample.object_id;
+        }
+    }
+}
+
+/// Per-pixel averages of the first-hit surface data, one buffer per pass. Scalar passes are
+/// replicated over the three channels and `uv` is stored as (u, v, 0).
+pub struct Aovs {
+    pub albedo: Framebuffer,
+    pub normal: Framebuffer,
+    pub depth: Framebuffer,
+    pub position: Framebuffer,
+    pub uv: Framebuffer,
+    pub object_id: Framebuffer,
+}
+
+impl Aovs {
+    /// Averages the per-pixel sums in `sums`, each of which holds `samples` camera rays.
+    pub fn from_sums(width: usize, height: usize, sums: &[(AovSample, u32)]) -> Self {
+        let pass = |f: &dyn Fn(&AovSample, f32) -> Vec3A| {
+            let pixels = sums
+                .iter()
+                .map(|(sum, samples)| f(sum, 1. / (*samples).max(1) as f32))
+                .collect();
+            Framebuffer::from_pixels(width, height, pixels)
+        };
+        Self {
+            albedo: pass(&|s, w| s.albedo * w),
+            normal: pass(&|s, w| s.normal * w),
+            depth: pass(&|s, w| Vec3A::splat(s.depth * w)),
+            position: pass(&|s, w| s.position * w),
+            uv: pass(&|s, w| (s.uv * w).extend(0.).into()),
+            object_id: pass(&|s, _| Vec3A::splat(s.object_id as f32)),
+        }
+    }
+
+    /// The passes cut down to `tile`, see [`Framebuffer::crop`].
+    pub fn crop(&self, tile: &Tile) -> Self {
+        self.map(|pass| pass.crop(tile))
+    }
+
+    /// The passes placed at `tile` in a blank frame, see [`Framebuffer::uncrop`].
+    pub fn uncrop(&self, tile: &Tile, width: usize, height: usize) -> Self {
+        self.map(|pass| pass.uncrop(tile, width, height))
+    }
+
+    fn map(&self, f: impl Fn(&Framebuffer) -> Framebuffer) -> Self {
+        Self {
+            albedo: f(&self.albedo),
+            normal: f(&self.normal),
+            depth: f(&self.depth),
+            position: f(&self.position),
+            uv: f(&self.uv),
+            object_id: f(&self.object_id),
+        }
+    }
+
+    pub fn passes(&self) -> [(&'static str, &Framebuffer); 6] {
+        [
+            ("albedo", &self.albedo),
+            ("normal", &self.normal),
+            ("depth", &self.depth),
+            ("position", &self.position),
+            ("uv", &self.uv),
+            ("object_id", &self.object_id),
+        ]
+    }
+
+    /// Writes every pass as a float OpenEXR next to the color output `path`, e.g.
+    /// `image.ppm` gives `image.albedo.exr`, `image.normal.exr` and so on.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        for (name, buffer) in self.passes() {
+            buffer.save_as(
+                pass_path(path.as_ref(), name),
+                OutputFormat::Exr,
+                &DisplayTransform::default(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn pass_path(path: &Path, name: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}.{}.exr", stem, name))
+}
@@ -1,17 +1,104 @@
-use std::{fs::OpenOptions, io::BufWriter, path::Path};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
 
-use anyhow::{Ok, Result};
-use feoh::draw;
+use anyhow::{bail, Ok, Result};
+use feoh::{
+    checkpoint::Checkpoint,
+    denoise::Denoiser,
+    output::OutputFormat,
+    progress::{CancellationToken, IndicatifProgress},
+    resume_with,
+    sampler::SamplerKind,
+    scene::cornell_box,
+    tonemap::{DisplayTransform, ToneMapper},
+    Rand, RenderSettings,
+};
+use rand::SeedableRng;
 
+/// Renders the Cornell box to the path given as argument, `image.ppm` by default. Optional
+/// features are switched on by flags: `--adaptive`, `--sobol`, `--denoise` (which also keeps the
+/// noisy image as `<name>.raw.<ext>`), `--aces`, `--aovs`, `--png16` for 16-bit PNG and `--p6`
+/// for binary PPM. Progress is saved to `<name>.ckpt` until the render finishes, and `--resume`
+/// continues from it.
 fn main() -> Result<()> {
-    let file = OpenOptions::new()
-        .create(true)
-        .truncate(true)
-        .write(true)
-        .open(Path::new("image.ppm"))?;
-    let mut writer = BufWriter::new(file);
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let flag = |name: &str| args.iter().any(|arg| arg == name);
+    let path = args
+        .iter()
+        .find(|arg| !arg.starts_with("--"))
+        .cloned()
+        .unwrap_or_else(|| "image.ppm".to_string());
+    // `.png` is 8-bit and `.ppm` ASCII unless asked otherwise.
+    let format = match OutputFormat::from_path(Path::new(&path))? {
+        OutputFormat::Png8 if flag("--png16") => OutputFormat::Png16,
+        OutputFormat::PpmAscii if flag("--p6") => OutputFormat::Ppm,
+        _ if flag("--png16") => bail!("--png16 needs a .png output path"),
+        _ if flag("--p6") => bail!("--p6 needs a .ppm output path"),
+        format => format,
+    };
+    let checkpoint_path = PathBuf::from(&path).with_extension("ckpt");
 
-    draw(600, 600, 1000, 50, &mut writer)?;
+    let settings = RenderSettings {
+        img_width: 600,
+        img_height: 600,
+        samples_per_pixel: 1000,
+        min_samples_per_pixel: 64,
+        noise_threshold: flag("--adaptive").then_some(0.01),
+        max_depth: 50,
+        sampler: if flag("--sobol") {
+            SamplerKind::Sobol
+        } else {
+            SamplerKind::default()
+        },
+        denoiser: flag("--denoise").then(|| Denoiser::new(1., 5, true)),
+        checkpoint_path: Some(checkpoint_path.clone()),
+        ..Default::default()
+    };
+    let mut rng = Rand::seed_from_u64(settings.seed);
+    let scene = cornell_box(settings.aspect_ratio(), &mut rng);
+
+    let checkpoint = if flag("--resume") {
+        Checkpoint::load(&checkpoint_path)?
+    } else {
+        Checkpoint::new(&scene, &settings)
+    };
+    let progress = IndicatifProgress::new()?;
+    let output = resume_with(
+        &scene,
+        &settings,
+        &progress,
+        &CancellationToken::default(),
+        checkpoint,
+    )?;
+    fs::remove_file(&checkpoint_path)?;
+    if output.stats.rejected_samples > 0 {
+        eprintln!(
+            "Rejected {} of {} samples as NaN or infinite",
+            output.stats.rejected_samples, output.stats.samples
+        );
+    }
+    let tone_mapper = if flag("--aces") {
+        ToneMapper::Aces
+    } else {
+        ToneMapper::default()
+    };
+    let display = DisplayTransform::new(0., tone_mapper);
+    output.image.save_as(&path, format, &display)?;
+    if let Some(raw) = &output.raw {
+        let path = Path::new(&path);
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let extension = path.extension().unwrap_or_default().to_string_lossy();
+        raw.save_as(
+            path.with_file_name(format!("{}.raw.{}", stem, extension)),
+            format,
+            &display,
+        )?;
+    }
+    if flag("--aovs") {
+        output.aovs.save(&path)?;
+    }
 
     Ok(())
 }
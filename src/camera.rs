@@ -1,17 +1,6 @@
 use glam::Vec3A;
-use rand::Rng;
 
-use crate::{ray::Ray, Rand};
-
-fn random_in_unit_disk(rng: &mut Rand) -> Vec3A {
-    let unit = Vec3A::new(1.0, 1.0, 0.0);
-    loop {
-        let p = 2.0 * Vec3A::new(rng.gen::<f32>(), rng.gen::<f32>(), 0.0) - unit;
-        if p.dot(p) < 1.0 {
-            return p;
-        }
-    }
-}
+use crate::{ray::Ray, sampler::Sampler, vec::random_in_unit_disk};
 
 pub struct Camera {
     origin: Vec3A,
@@ -54,13 +43,28 @@ impl Camera {
         }
     }
 
-    pub fn get_ray(&self, u: f32, v: f32, rng: &mut Rand) -> Ray {
-        let rd = self.lens_radius * random_in_unit_disk(rng);
+    pub fn get_ray(&self, u: f32, v: f32, sampler: &mut dyn Sampler) -> Ray {
+        let rd = self.lens_radius * random_in_unit_disk(sampler.get_2d());
         let offset = self.u * rd.x + self.v * rd.y;
         Ray::new(
             self.origin + offset,
             self.lower_left_corner + u * self.horizontal + v * self.vertical - self.origin - offset,
-            rng.gen_range(self.time.0..self.time.1),
+            self.time.0 + sampler.get_1d() * (self.time.1 - self.time.0),
         )
     }
+
+    /// Every number the rays depend on, for [`Scene::fingerprint`](crate::scene::Scene::fingerprint).
+    pub fn parameters(&self) -> impl Iterator<Item = f32> {
+        [
+            self.origin,
+            self.lower_left_corner,
+            self.horizontal,
+            self.vertical,
+            self.u,
+            self.v,
+        ]
+        .into_iter()
+        .flat_map(|v| v.to_array())
+        .chain([self.lens_radius, self.time.0, self.time.1])
+    }
 }
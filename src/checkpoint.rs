@@ -0,0 +1,599 @@
+use crate::{
+    aov::{AovSample, Aovs},
+    film::{FilmPixel, TileFilm},
+    framebuffer::Framebuffer,
+    integrator::{debug::DebugMode, Integrator},
+    sampler::SamplerKind,
+    scene::Scene,
+    tile::{tiles, Tile},
+    RenderSettings, RenderStats,
+};
+use anyhow::{bail, ensure, Result};
+use glam::{Vec2, Vec3A};
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+const MAGIC: &[u8; 8] = b"FEOHCKPT";
+const VERSION: u32 = 7;
+/// Bytes of one [`PixelState`] and one [`FilmPixel`] in the file.
+const PIXEL_BYTES: usize = 80;
+const FILM_PIXEL_BYTES: usize = 16;
+
+/// Everything a pixel has accumulated so far; enough to continue sampling it exactly where it
+/// stopped.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct PixelState {
+    /// Sum of the accepted samples.
+    pub sum: Vec3A,
+    /// Samples accepted into `sum`, the luminance statistics and the AOVs.
+    pub samples: u32,
+    /// Running mean of the sample luminance, for adaptive sampling.
+    pub mean: f32,
+    /// Running sum of squared luminance deviations, for adaptive sampling.
+    pub m2: f32,
+    /// Samples that came back NaN or infinite and were left out of everything else.
+    pub rejected: u32,
+    /// Sum of the first-hit AOVs of the samples.
+    pub aov: AovSample,
+}
+
+impl PixelState {
+    pub fn radiance(&self) -> Vec3A {
+        self.sum / self.samples.max(1) as f32
+    }
+
+    /// Samples taken so far, rejected or not; the index of the next one.
+    pub fn traced(&self) -> u32 {
+        self.samples + self.rejected
+    }
+}
+
+/// Accumulated state of a whole render, which can be saved and later resumed.
+#[derive(Clone)]
+pub struct Checkpoint {
+    pub width: usize,
+    pub height: usize,
+    pub seed: u64,
+    pub sampler: SamplerKind,
+    /// Sample budget the render was started with, which sets the layout of the stratified
+    /// sampler.
+    pub samples_per_pixel: usize,
+    pub tile_size: usize,
+    pub filter_radius: f32,
+    pub integrator: Integrator,
+    pub spectral: bool,
+    pub max_depth: usize,
+    pub rr_min_depth: usize,
+    /// Pixels the render returns, see [`RenderSettings::window`].
+    pub window: Tile,
+    pub noise_threshold: Option<f32>,
+    pub min_samples_per_pixel: usize,
+    /// [`Scene::fingerprint`] of the rendered scene.
+    pub scene: u64,
+    pub pixels: Vec<PixelState>,
+    /// Splatted samples of every tile, in the order of [`tiles`].
+    pub films: Vec<TileFilm>,
+}
+
+impl Checkpoint {
+    pub fn new(scene: &Scene, settings: &RenderSettings) -> Self {
+        Self {
+            width: settings.img_width,
+            height: settings.img_height,
+            seed: settings.seed,
+            sampler: settings.sampler,
+            samples_per_pixel: settings.samples_per_pixel,
+            tile_size: settings.tile_size,
+            filter_radius: settings.filter.radius(),
+            integrator: settings.integrator,
+            spectral: settings.spectral,
+            max_depth: settings.max_depth,
+            rr_min_depth: settings.rr_min_depth,
+            window: settings.window(),
+            noise_threshold: settings.noise_threshold,
+            min_samples_per_pixel: settings.min_samples_per_pixel,
+            scene: scene.fingerprint(),
+            pixels: Vec::new(),
+            films: Vec::new(),
+        }
+        .allocate()
+    }
+
+    /// Fills in empty pixels and films for the header.
+    fn allocate(self) -> Self {
+        let films = self
+            .tiles()
+            .iter()
+            .map(|tile| TileFilm::new(tile, self.filter_radius, self.width, self.height))
+            .collect();
+        Self {
+            pixels: vec![PixelState::default(); self.width * self.height],
+            films,
+            ..self
+        }
+    }
+
+    fn tiles(&self) -> Vec<Tile> {
+        tiles(self.width, self.height, self.tile_size)
+    }
+
+    pub fn read_tile(&self, tile: &Tile) -> Vec<PixelState> {
+        tile.pixels()
+            .map(|(x, y)| self.pixels[y * self.width + x])
+            .collect()
+    }
+
+    pub fn write_tile(&mut self, tile: &Tile, pixels: &[PixelState]) {
+        for ((x, y), pixel) in tile.pixels().zip(pixels) {
+            self.pixels[y * self.width + x] = *pixel;
+        }
+    }
+
+    pub fn stats(&self) -> RenderStats {
+        RenderStats {
+            samples: self.pixels.iter().map(|p| p.traced() as u64).sum(),
+            rejected_samples: self.pixels.iter().map(|p| p.rejected as u64).sum(),
+        }
+    }
+
+    pub fn aovs(&self) -> Aovs {
+        let sums = self
+            .pixels
+            .iter()
+            .map(|p| (p.aov, p.samples))
+            .collect::<Vec<_>>();
+        Aovs::from_sums(self.width, self.height, &sums)
+    }
+
+    /// Merges the tile films in a fixed order and normalizes every pixel by its filter weight.
+    pub fn framebuffer(&self) -> Framebuffer {
+        let mut film = vec![FilmPixel::default(); self.width * self.height];
+        for tile_film in &self.films {
+            for ((x, y), splat) in tile_film.coords().zip(&tile_film.pixels) {
+                let pixel = &mut film[y * self.width + x];
+                pixel.weighted_sum += splat.weighted_sum;
+                pixel.weight += splat.weight;
+            }
+        }
+
+        let pixels = film.iter().map(FilmPixel::radiance).collect();
+        Framebuffer::from_pixels(self.width, self.height, pixels)
+    }
+
+    /// Writes the checkpoint next to `path` first and then renames it, so an interrupted save
+    /// never clobbers the previous checkpoint.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let partial = path.with_extension("partial");
+        {
+            let mut writer = BufWriter::new(File::create(&partial)?);
+            writer.write_all(MAGIC)?;
+            writer.write_all(&VERSION.to_le_bytes())?;
+            writer.write_all(&(self.width as u64).to_le_bytes())?;
+            writer.write_all(&(self.height as u64).to_le_bytes())?;
+            writer.write_all(&self.seed.to_le_bytes())?;
+            writer.write_all(&(self.sampler as u32).to_le_bytes())?;
+            writer.write_all(&(self.samples_per_pixel as u64).to_le_bytes())?;
+            writer.write_all(&(self.tile_size as u64).to_le_bytes())?;
+            writer.write_all(&self.filter_radius.to_le_bytes())?;
+            let (integrator, parameter) = encode_integrator(self.integrator);
+            writer.write_all(&integrator.to_le_bytes())?;
+            writer.write_all(&parameter.to_le_bytes())?;
+            writer.write_all(&(self.spectral as u32).to_le_bytes())?;
+            writer.write_all(&(self.max_depth as u64).to_le_bytes())?;
+            writer.write_all(&(self.rr_min_depth as u64).to_le_bytes())?;
+            for value in [
+                self.window.x,
+                self.window.y,
+                self.window.width,
+                self.window.height,
+            ] {
+                writer.write_all(&(value as u64).to_le_bytes())?;
+            }
+            writer.write_all(&(self.noise_threshold.is_some() as u32).to_le_bytes())?;
+            writer.write_all(&self.noise_threshold.unwrap_or(0.).to_le_bytes())?;
+            writer.write_all(&(self.min_samples_per_pixel as u64).to_le_bytes())?;
+            writer.write_all(&self.scene.to_le_bytes())?;
+            for pixel in &self.pixels {
+                for c in pixel.sum.to_array() {
+                    writer.write_all(&c.to_le_bytes())?;
+                }
+                writer.write_all(&pixel.samples.to_le_bytes())?;
+                writer.write_all(&pixel.mean.to_le_bytes())?;
+                writer.write_all(&pixel.m2.to_le_bytes())?;
+                writer.write_all(&pixel.rejected.to_le_bytes())?;
+                let aov = &pixel.aov;
+                for c in aov
+                    .albedo
+                    .to_array()
+                    .into_iter()
+                    .chain(aov.normal.to_array())
+                    .chain([aov.depth])
+                    .chain(aov.position.to_array())
+                    .chain(aov.uv.to_array())
+                {
+                    writer.write_all(&c.to_le_bytes())?;
+                }
+                writer.write_all(&aov.object_id.to_le_bytes())?;
+            }
+            for pixel in self.films.iter().flat_map(|film| &film.pixels) {
+                for c in pixel.weighted_sum.to_array() {
+                    writer.write_all(&c.to_le_bytes())?;
+                }
+                writer.write_all(&pixel.weight.to_le_bytes())?;
+            }
+            writer.flush()?;
+        }
+        fs::rename(&partial, path)?;
+
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let bytes = fs::read(path.as_ref())?;
+        let mut reader = Reader { bytes: &bytes };
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            bail!("Not a checkpoint: {}", path.as_ref().display());
+        }
+        let version = reader.u32()?;
+        ensure!(
+            version == VERSION,
+            "Unsupported checkpoint version {}",
+            version
+        );
+
+        let width = reader.u64()? as usize;
+        let height = reader.u64()? as usize;
+        let seed = reader.u64()?;
+        let sampler = match reader.u32()? {
+            0 => SamplerKind::Independent,
+            1 => SamplerKind::Stratified,
+            2 => SamplerKind::Halton,
+            3 => SamplerKind::Sobol,
+            other => bail!("Unknown sampler {} in checkpoint", other),
+        };
+        let samples_per_pixel = reader.u64()? as usize;
+        let tile_size = reader.u64()? as usize;
+        let filter_radius = reader.f32()?;
+        let integrator = decode_integrator(reader.u32()?, reader.u32()?)?;
+        let spectral = reader.u32()? != 0;
+        let max_depth = reader.u64()? as usize;
+        let rr_min_depth = reader.u64()? as usize;
+        let window = Tile {
+            x: reader.u64()? as usize,
+            y: reader.u64()? as usize,
+            width: reader.u64()? as usize,
+            height: reader.u64()? as usize,
+        };
+        let adaptive = reader.u32()? != 0;
+        let noise_threshold = reader.f32()?;
+        let noise_threshold = adaptive.then_some(noise_threshold);
+        let min_samples_per_pixel = reader.u64()? as usize;
+        let scene = reader.u64()?;
+
+        let header = Self {
+            width,
+            height,
+            seed,
+            sampler,
+            samples_per_pixel,
+            tile_size,
+            filter_radius,
+            integrator,
+            spectral,
+            max_depth,
+            rr_min_depth,
+            window,
+            noise_threshold,
+            min_samples_per_pixel,
+            scene,
+            pixels: Vec::new(),
+            films: Vec::new(),
+        };
+        // Check the size the header promises against the file before allocating for it, so a
+        // corrupt header fails cleanly.
+        let pixel_bytes = width
+            .checked_mul(height)
+            .and_then(|pixels| pixels.checked_mul(PIXEL_BYTES))
+            .filter(|&bytes| bytes <= reader.bytes.len());
+        let Some(pixel_bytes) = pixel_bytes else {
+            bail!("Checkpoint size does not match its header");
+        };
+        let film_bytes = header
+            .tiles()
+            .iter()
+            .map(|tile| TileFilm::footprint(tile, filter_radius, width, height))
+            .try_fold(0usize, |sum, film| {
+                sum.checked_add(film.width * film.height * FILM_PIXEL_BYTES)
+            });
+        ensure!(
+            film_bytes.and_then(|film_bytes| film_bytes.checked_add(pixel_bytes))
+                == Some(reader.bytes.len()),
+            "Checkpoint size does not match its header"
+        );
+
+        let mut checkpoint = header.allocate();
+        checkpoint.pixels = (0..width * height)
+            .map(|_| {
+                Ok(PixelState {
+                    sum: Vec3A::new(reader.f32()?, reader.f32()?, reader.f32()?),
+                    samples: reader.u32()?,
+                    mean: reader.f32()?,
+                    m2: reader.f32()?,
+                    rejected: reader.u32()?,
+                    aov: AovSample {
+                        albedo: Vec3A::new(reader.f32()?, reader.f32()?, reader.f32()?),
+                        normal: Vec3A::new(reader.f32()?, reader.f32()?, reader.f32()?),
+                        depth: reader.f32()?,
+                        position: Vec3A::new(reader.f32()?, reader.f32()?, reader.f32()?),
+                        uv: Vec2::new(reader.f32()?, reader.f32()?),
+                        object_id: reader.u32()?,
+                    },
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for pixel in checkpoint
+            .films
+            .iter_mut()
+            .flat_map(|film| &mut film.pixels)
+        {
+            *pixel = FilmPixel {
+                weighted_sum: Vec3A::new(reader.f32()?, reader.f32()?, reader.f32()?),
+                weight: reader.f32()?,
+            };
+        }
+
+        Ok(checkpoint)
+    }
+}
+
+/// Integrator as a tag and the bits of the debug mode's parameter, if it has one.
+fn encode_integrator(integrator: Integrator) -> (u32, u32) {
+    match integrator {
+        Integrator::PathTracer => (0, 0),
+        Integrator::Bidirectional => (1, 0),
+        Integrator::PhotonMapping => (2, 0),
+        Integrator::Debug(DebugMode::Normal) => (3, 0),
+        Integrator::Debug(DebugMode::Uv) => (4, 0),
+        Integrator::Debug(DebugMode::Depth { max_distance }) => (5, max_distance.to_bits()),
+        Integrator::Debug(DebugMode::FrontFace) => (6, 0),
+        Integrator::Debug(DebugMode::AabbTests { max }) => (7, max),
+        Integrator::Debug(DebugMode::NodeVisits { max }) => (8, max),
+    }
+}
+
+fn decode_integrator(tag: u32, parameter: u32) -> Result<Integrator> {
+    Ok(match tag {
+        0 => Integrator::PathTracer,
+        1 => Integrator::Bidirectional,
+        2 => Integrator::PhotonMapping,
+        3 => Integrator::Debug(DebugMode::Normal),
+        4 => Integrator::Debug(DebugMode::Uv),
+        5 => Integrator::Debug(DebugMode::Depth {
+            max_distance: f32::from_bits(parameter),
+        }),
+        6 => Integrator::Debug(DebugMode::FrontFace),
+        7 => Integrator::Debug(DebugMode::AabbTests { max: parameter }),
+        8 => Integrator::Debug(DebugMode::NodeVisits { max: parameter }),
+        other => bail!("Unknown integrator {} in checkpoint", other),
+    })
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        ensure!(self.bytes.len() >= len, "Truncated checkpoint");
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn f32(&mut self) -> Result<f32> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        crop::{CropRegion, CropWindow},
+        progress::{CancellationToken, ProgressSink, SilentProgress},
+        render, resume_with,
+        scene::{cornell_box, cornell_smoke, Scene},
+        Rand,
+    };
+    use rand::SeedableRng;
+    use rayon::ThreadPoolBuilder;
+    use std::{env, path::PathBuf, process};
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("feoh-{}-{}.ckpt", process::id(), name))
+    }
+
+    fn tiny_render(checkpoint_path: Option<PathBuf>) -> (Scene, RenderSettings) {
+        let settings = RenderSettings {
+            img_width: 24,
+            img_height: 16,
+            samples_per_pixel: 4,
+            tile_size: 8,
+            checkpoint_path,
+            ..Default::default()
+        };
+        let scene = cornell_box(
+            settings.aspect_ratio(),
+            &mut Rand::seed_from_u64(settings.seed),
+        );
+        (scene, settings)
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let path = temp_path("round-trip");
+        let copy = temp_path("round-trip-copy");
+        let (scene, settings) = tiny_render(Some(path.clone()));
+        let output = render(&scene, &settings).unwrap();
+
+        let loaded = Checkpoint::load(&path).unwrap();
+        loaded.save(&copy).unwrap();
+        let reloaded = Checkpoint::load(&copy).unwrap();
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&copy).unwrap();
+
+        assert_eq!(loaded.framebuffer().pixels, output.image.pixels);
+        assert_eq!(loaded.stats(), output.stats);
+        assert_eq!(
+            (loaded.width, loaded.height, loaded.seed, loaded.tile_size),
+            (
+                reloaded.width,
+                reloaded.height,
+                reloaded.seed,
+                reloaded.tile_size
+            )
+        );
+        assert_eq!(
+            (loaded.sampler, loaded.samples_per_pixel),
+            (reloaded.sampler, reloaded.samples_per_pixel)
+        );
+        assert_eq!(loaded.filter_radius, reloaded.filter_radius);
+        assert_eq!(
+            (loaded.integrator, loaded.spectral, loaded.window),
+            (reloaded.integrator, reloaded.spectral, reloaded.window)
+        );
+        assert_eq!(
+            (loaded.max_depth, loaded.rr_min_depth, loaded.scene),
+            (reloaded.max_depth, reloaded.rr_min_depth, reloaded.scene)
+        );
+        assert_eq!(
+            (loaded.noise_threshold, loaded.min_samples_per_pixel),
+            (reloaded.noise_threshold, reloaded.min_samples_per_pixel)
+        );
+        assert_eq!(loaded.pixels, reloaded.pixels);
+        assert_eq!(loaded.films, reloaded.films);
+    }
+
+    #[test]
+    fn load_rejects_header_larger_than_file() {
+        let path = temp_path("huge");
+        let (scene, settings) = tiny_render(None);
+        Checkpoint::new(&scene, &settings).save(&path).unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        // The width follows the magic and the version.
+        bytes[12..20].copy_from_slice(&(u64::MAX / 2).to_le_bytes());
+        fs::write(&path, bytes).unwrap();
+
+        let loaded = Checkpoint::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(loaded.is_err());
+    }
+
+    /// Cancels the render as soon as the first tile is done.
+    struct CancelAfterFirstTile(CancellationToken);
+
+    impl ProgressSink for CancelAfterFirstTile {
+        fn advance(&self, _amount: u64) {
+            self.0.cancel();
+        }
+    }
+
+    #[test]
+    fn resume_after_cancel_matches_uninterrupted_render() {
+        let path = temp_path("resume");
+        let (scene, settings) = tiny_render(Some(path.clone()));
+        let cancel = CancellationToken::default();
+        // One thread, so the cancellation lands before the other tiles are started.
+        let pool = ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let cancelled = pool.install(|| {
+            resume_with(
+                &scene,
+                &settings,
+                &CancelAfterFirstTile(cancel.clone()),
+                &cancel,
+                Checkpoint::new(&scene, &settings),
+            )
+        });
+        assert!(cancelled.is_err());
+
+        let checkpoint = Checkpoint::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let full = (settings.img_width * settings.img_height * settings.samples_per_pixel) as u64;
+        assert!(checkpoint.stats().samples < full);
+
+        let (scene, settings) = tiny_render(None);
+        let resumed = resume_with(
+            &scene,
+            &settings,
+            &SilentProgress,
+            &CancellationToken::default(),
+            checkpoint,
+        )
+        .unwrap();
+        let uninterrupted = render(&scene, &settings).unwrap();
+        assert_eq!(resumed.image.pixels, uninterrupted.image.pixels);
+        assert_eq!(resumed.stats, uninterrupted.stats);
+    }
+
+    #[test]
+    fn resume_refuses_checkpoint_of_other_render() {
+        let (scene, settings) = tiny_render(None);
+        let checkpoint = Checkpoint::new(&scene, &settings);
+        let changes: [fn(&mut RenderSettings); 6] = [
+            |settings| settings.integrator = Integrator::Debug(DebugMode::Normal),
+            |settings| settings.spectral = true,
+            |settings| settings.max_depth = 5,
+            |settings| settings.rr_min_depth = 1,
+            |settings| {
+                settings.crop = Some(CropWindow::new(
+                    CropRegion::Pixels(Tile {
+                        x: 0,
+                        y: 0,
+                        width: 8,
+                        height: 8,
+                    }),
+                    false,
+                ))
+            },
+            |settings| settings.noise_threshold = Some(0.01),
+        ];
+        for change in changes {
+            let (scene, mut settings) = tiny_render(None);
+            change(&mut settings);
+            let resumed = resume_with(
+                &scene,
+                &settings,
+                &SilentProgress,
+                &CancellationToken::default(),
+                checkpoint.clone(),
+            );
+            assert!(resumed.is_err());
+        }
+
+        let other_scene = cornell_smoke(
+            settings.aspect_ratio(),
+            &mut Rand::seed_from_u64(settings.seed),
+        );
+        let resumed = resume_with(
+            &other_scene,
+            &settings,
+            &SilentProgress,
+            &CancellationToken::default(),
+            checkpoint,
+        );
+        assert!(resumed.is_err());
+    }
+}
@@ -0,0 +1,48 @@
+use crate::tile::Tile;
+use glam::Vec2;
+
+/// Rectangle of the frame to render.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CropRegion {
+    /// Pixel rectangle, row 0 being the top of the image.
+    Pixels(Tile),
+    /// Corners as fractions of the frame size, (0, 0) being the top-left corner. Partially
+    /// covered pixels are included.
+    Normalized { min: Vec2, max: Vec2 },
+}
+
+/// Part of the frame to trace, see [`RenderSettings::crop`](crate::RenderSettings::crop). The
+/// camera still maps the full frame, so the window lines up with a full render.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CropWindow {
+    pub region: CropRegion,
+    /// Return the whole frame with black outside the window instead of only the window.
+    pub full_frame: bool,
+}
+
+impl CropWindow {
+    pub fn new(region: CropRegion, full_frame: bool) -> Self {
+        Self { region, full_frame }
+    }
+
+    /// Pixels covered by the window in a `width` x `height` frame, clipped to the frame.
+    pub fn pixels(&self, width: usize, height: usize) -> Tile {
+        let (x0, y0, x1, y1) = match self.region {
+            CropRegion::Pixels(tile) => (tile.x, tile.y, tile.x + tile.width, tile.y + tile.height),
+            CropRegion::Normalized { min, max } => (
+                (min.x * width as f32).floor() as usize,
+                (min.y * height as f32).floor() as usize,
+                (max.x * width as f32).ceil() as usize,
+                (max.y * height as f32).ceil() as usize,
+            ),
+        };
+        let (x1, y1) = (x1.min(width), y1.min(height));
+        let (x0, y0) = (x0.min(x1), y0.min(y1));
+        Tile {
+            x: x0,
+            y: y0,
+            width: x1 - x0,
+            height: y1 - y0,
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{aov::Aovs, framebuffer::Framebuffer};
+use glam::Vec3A;
+use rayon::prelude::{IndexedParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
+
+/// B3-spline taps of the à-trous kernel.
+const KERNEL: [f32; 5] = [1. / 16., 1. / 4., 3. / 8., 1. / 4., 1. / 16.];
+
+/// Edge-avoiding à-trous wavelet filter (Dammertz et al. 2010) guided by the albedo, normal and
+/// depth passes. The radiance is divided by the albedo before filtering and multiplied back
+/// afterwards, so textures stay sharp while the lighting is smoothed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Denoiser {
+    /// Scales how different two pixels may look and still be averaged; 0 leaves the image
+    /// untouched.
+    pub strength: f32,
+    /// Number of passes; pass `i` reaches `2^(i + 1)` pixels out.
+    pub iterations: usize,
+    /// Keep the noisy image next to the denoised one in the render output.
+    pub keep_raw: bool,
+}
+
+impl Denoiser {
+    pub fn new(strength: f32, iterations: usize, keep_raw: bool) -> Self {
+        Self {
+            strength,
+            iterations,
+            keep_raw,
+        }
+    }
+
+    pub fn apply(&self, image: &Framebuffer, aovs: &Aovs) -> Framebuffer {
+        if self.strength <= 0. {
+            return Framebuffer::from_pixels(image.width, image.height, image.pixels.clone());
+        }
+
+        let albedo = aovs
+            .albedo
+            .pixels
+            .iter()
+            .map(|a| Vec3A::select(a.cmpgt(Vec3A::splat(1e-3)), *a, Vec3A::ONE))
+            .collect::<Vec<_>>();
+        let mut irradiance = image
+            .pixels
+            .iter()
+            .zip(&albedo)
+            .map(|(c, a)| *c / *a)
+            .collect::<Vec<_>>();
+
+        let mut sigma_color = 0.5 * self.strength;
+        for iteration in 0..self.iterations {
+            irradiance = self.pass(image, aovs, &irradiance, 1 << iteration, sigma_color);
+            sigma_color *= 0.5;
+        }
+
+        let pixels = irradiance
+            .iter()
+            .zip(&albedo)
+            .map(|(c, a)| *c * *a)
+            .collect();
+        Framebuffer::from_pixels(image.width, image.height, pixels)
+    }
+
+    fn pass(
+        &self,
+        image: &Framebuffer,
+        aovs: &Aovs,
+        input: &[Vec3A],
+        step: usize,
+        sigma_color: f32,
+    ) -> Vec<Vec3A> {
+        let (width, height) = (image.width, image.height);
+        let sigma_normal = 0.1 * self.strength;
+        let sigma_depth = 0.05 * self.strength;
+        let normals = &aovs.normal.pixels;
+        let depths = &aovs.depth.pixels;
+
+        let mut output = vec![Vec3A::ZERO; input.len()];
+        output.par_iter_mut().enumerate().for_each(|(p, out)| {
+            let (x, y) = (p % width, p / width);
+            // Compare tone-compressed colours so a few bright outliers do not dominate.
+            let color_p = compress(input[p]);
+            let normal_p = normals[p];
+            let depth_p = depths[p].x;
+
+            let mut sum = Vec3A::ZERO;
+            let mut weight_sum = 0.;
+            for (j, ky) in KERNEL.iter().enumerate() {
+                let qy = y as isize + (j as isize - 2) * step as isize;
+                if qy < 0 || qy >= height as isize {
+                    continue;
+                }
+                for (i, kx) in KERNEL.iter().enumerate() {
+                    let qx = x as isize + (i as isize - 2) * step as isize;
+                    if qx < 0 || qx >= width as isize {
+                        continue;
+                    }
+                    let q = qy as usize * width + qx as usize;
+
+                    let color_dist = (compress(input[q]) - color_p).length_squared();
+                    let normal_dist = (normals[q] - normal_p).length_squared();
+                    let depth_dist =
+                        ((depths[q].x - depth_p) / (depth_p.max(1e-3) * step as f32)).powi(2);
+                    let weight = kx
+                        * ky
+                        * (-color_dist / (sigma_color * sigma_color)
+                            - normal_dist / (sigma_normal * sigma_normal)
+                            - depth_dist / (sigma_depth * sigma_depth))
+                            .exp();
+
+                    sum += weight * input[q];
+                    weight_sum += weight;
+                }
+            }
+            // The centre tap always keeps its kernel weight, so the sum is positive.
+            *out = sum / weight_sum;
+        });
+        output
+    }
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self::new(1., 5, false)
+    }
+}
+
+fn compress(color: Vec3A) -> Vec3A {
+    color / (Vec3A::ONE + color)
+}
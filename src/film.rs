@@ -0,0 +1,122 @@
+use crate::{filter::Filter, tile::Tile};
+use glam::Vec3A;
+
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct FilmPixel {
+    pub weighted_sum: Vec3A,
+    pub weight: f32,
+}
+
+impl FilmPixel {
+    pub fn radiance(&self) -> Vec3A {
+        if self.weight > 0. {
+            self.weighted_sum / self.weight
+        } else {
+            Vec3A::ZERO
+        }
+    }
+}
+
+/// Splats of the samples taken inside one tile. The region extends past the tile by the filter
+/// footprint, clipped to the image, so neighbouring tiles' films overlap at their borders.
+/// Keeping them apart until the end lets every film be filled by a single thread, which keeps
+/// the final image independent of the order tiles finish in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileFilm {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<FilmPixel>,
+}
+
+/// Pixels a sample can reach on each side of the one it was taken in.
+pub fn filter_margin(radius: f32) -> usize {
+    ((radius + 0.5).ceil() as usize).saturating_sub(1)
+}
+
+impl TileFilm {
+    pub fn new(tile: &Tile, radius: f32, img_width: usize, img_height: usize) -> Self {
+        let Tile {
+            x,
+            y,
+            width,
+            height,
+        } = Self::footprint(tile, radius, img_width, img_height);
+        Self {
+            x,
+            y,
+            width,
+            height,
+            pixels: vec![FilmPixel::default(); width * height],
+        }
+    }
+
+    /// Region the film of `tile` covers.
+    pub fn footprint(tile: &Tile, radius: f32, img_width: usize, img_height: usize) -> Tile {
+        let margin = filter_margin(radius);
+        let x = tile.x.saturating_sub(margin);
+        let y = tile.y.saturating_sub(margin);
+        Tile {
+            x,
+            y,
+            width: (tile.x + tile.width).saturating_add(margin).min(img_width) - x,
+            height: (tile.y + tile.height)
+                .saturating_add(margin)
+                .min(img_height)
+                - y,
+        }
+    }
+
+    /// Adds a sample at film position `(fx, fy)`, in pixels from the top-left corner, to every
+    /// pixel whose centre is within the filter radius.
+    pub fn splat(&mut self, filter: &dyn Filter, fx: f32, fy: f32, color: Vec3A) {
+        let radius = filter.radius();
+        let x0 = ((fx - 0.5 - radius).floor() as isize + 1).max(self.x as isize);
+        let x1 = ((fx - 0.5 + radius).floor() as isize).min((self.x + self.width) as isize - 1);
+        let y0 = ((fy - 0.5 - radius).floor() as isize + 1).max(self.y as isize);
+        let y1 = ((fy - 0.5 + radius).floor() as isize).min((self.y + self.height) as isize - 1);
+
+        for py in y0..=y1 {
+            for px in x0..=x1 {
+                let weight = filter.evaluate(fx - (px as f32 + 0.5), fy - (py as f32 + 0.5));
+                if weight != 0. {
+                    let index = (py as usize - self.y) * self.width + (px as usize - self.x);
+                    let pixel = &mut self.pixels[index];
+                    pixel.weighted_sum += weight * color;
+                    pixel.weight += weight;
+                }
+            }
+        }
+    }
+
+    /// Film coordinates covered by the region, in row-major order.
+    pub fn coords(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (self.y..self.y + self.height)
+            .flat_map(move |y| (self.x..self.x + self.width).map(move |x| (x, y)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_margin_covers_the_farthest_reachable_pixel() {
+        for radius in [0.5, 0.75, 1., 1.5, 1.6, 2., 3.2] {
+            let margin = filter_margin(radius);
+            // Pixels to the right a sample at `offset` within its pixel reaches.
+            let reached = (0..100)
+                .map(|i| {
+                    let offset = i as f32 / 100.;
+                    (0..10)
+                        .filter(|&n| (n as f32 + 0.5 - offset).abs() <= radius)
+                        .max()
+                        .unwrap_or(0)
+                })
+                .max()
+                .unwrap();
+            assert_eq!(margin, reached, "radius {}", radius);
+        }
+    }
+}
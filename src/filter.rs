@@ -0,0 +1,259 @@
+use std::f32::consts::PI;
+
+/// Pixel reconstruction filter. Every sample is splatted into all pixels whose centre lies
+/// within `radius` of it, weighted by `evaluate`, and each pixel is normalized by the sum of
+/// the weights it received.
+pub trait Filter: Sync + Send {
+    /// Half-width of the square support, in pixels.
+    fn radius(&self) -> f32;
+    /// Weight of a sample at offset `(x, y)` from a pixel centre.
+    fn evaluate(&self, x: f32, y: f32) -> f32;
+}
+
+/// Equal weight over the support. With the default radius of half a pixel every sample only
+/// lands in the pixel it was taken in.
+pub struct BoxFilter {
+    radius: f32,
+}
+
+impl BoxFilter {
+    pub fn new(radius: f32) -> Self {
+        Self { radius }
+    }
+}
+
+impl Default for BoxFilter {
+    fn default() -> Self {
+        Self::new(0.5)
+    }
+}
+
+impl Filter for BoxFilter {
+    fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f32, y: f32) -> f32 {
+        if x.abs() <= self.radius && y.abs() <= self.radius {
+            1.
+        } else {
+            0.
+        }
+    }
+}
+
+/// Tent filter falling off linearly to zero at the radius.
+pub struct TriangleFilter {
+    radius: f32,
+}
+
+impl TriangleFilter {
+    pub fn new(radius: f32) -> Self {
+        Self { radius }
+    }
+}
+
+impl Default for TriangleFilter {
+    fn default() -> Self {
+        Self::new(1.)
+    }
+}
+
+impl Filter for TriangleFilter {
+    fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f32, y: f32) -> f32 {
+        (self.radius - x.abs()).max(0.) * (self.radius - y.abs()).max(0.)
+    }
+}
+
+/// Gaussian of falloff `alpha`, shifted down so it reaches zero at the radius.
+pub struct GaussianFilter {
+    radius: f32,
+    alpha: f32,
+    edge: f32,
+}
+
+impl GaussianFilter {
+    pub fn new(radius: f32, alpha: f32) -> Self {
+        Self {
+            radius,
+            alpha,
+            edge: (-alpha * radius * radius).exp(),
+        }
+    }
+
+    fn gaussian(&self, d: f32) -> f32 {
+        ((-self.alpha * d * d).exp() - self.edge).max(0.)
+    }
+}
+
+impl Default for GaussianFilter {
+    fn default() -> Self {
+        Self::new(1.5, 2.)
+    }
+}
+
+impl Filter for GaussianFilter {
+    fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f32, y: f32) -> f32 {
+        self.gaussian(x) * self.gaussian(y)
+    }
+}
+
+/// Mitchell-Netravali cubic. `b = c = 1/3` is the compromise the authors recommend.
+pub struct MitchellFilter {
+    radius: f32,
+    b: f32,
+    c: f32,
+}
+
+impl MitchellFilter {
+    pub fn new(radius: f32, b: f32, c: f32) -> Self {
+        Self { radius, b, c }
+    }
+
+    /// The cubic is defined on `[-2, 2]`, so `d` is rescaled from the filter's support.
+    fn mitchell(&self, d: f32) -> f32 {
+        let x = (2. * d / self.radius).abs();
+        let (b, c) = (self.b, self.c);
+        if x > 2. {
+            0.
+        } else if x > 1. {
+            ((-b - 6. * c) * x * x * x
+                + (6. * b + 30. * c) * x * x
+                + (-12. * b - 48. * c) * x
+                + (8. * b + 24. * c))
+                / 6.
+        } else {
+            ((12. - 9. * b - 6. * c) * x * x * x
+                + (-18. + 12. * b + 6. * c) * x * x
+                + (6. - 2. * b))
+                / 6.
+        }
+    }
+}
+
+impl Default for MitchellFilter {
+    fn default() -> Self {
+        Self::new(2., 1. / 3., 1. / 3.)
+    }
+}
+
+impl Filter for MitchellFilter {
+    fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f32, y: f32) -> f32 {
+        self.mitchell(x) * self.mitchell(y)
+    }
+}
+
+/// Sinc windowed by a wider sinc that reaches its first zero at the radius.
+pub struct LanczosFilter {
+    radius: f32,
+}
+
+impl LanczosFilter {
+    pub fn new(radius: f32) -> Self {
+        Self { radius }
+    }
+
+    fn lanczos(&self, d: f32) -> f32 {
+        if d.abs() >= self.radius {
+            0.
+        } else {
+            sinc(d) * sinc(d / self.radius)
+        }
+    }
+}
+
+impl Default for LanczosFilter {
+    fn default() -> Self {
+        Self::new(2.)
+    }
+}
+
+impl Filter for LanczosFilter {
+    fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f32, y: f32) -> f32 {
+        self.lanczos(x) * self.lanczos(y)
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-5 {
+        1.
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filters() -> [Box<dyn Filter>; 5] {
+        [
+            Box::new(BoxFilter::default()),
+            Box::new(TriangleFilter::default()),
+            Box::new(GaussianFilter::default()),
+            Box::new(MitchellFilter::default()),
+            Box::new(LanczosFilter::default()),
+        ]
+    }
+
+    #[test]
+    fn weights_vanish_outside_the_radius_and_are_symmetric() {
+        for filter in filters() {
+            let radius = filter.radius();
+            let peak = filter.evaluate(0., 0.);
+            assert!(peak > 0.);
+            for i in 0..=40 {
+                let d = radius * i as f32 / 20.;
+                let weight = filter.evaluate(d, 0.3 * d);
+                assert_eq!(weight, filter.evaluate(-d, -0.3 * d));
+                assert_eq!(weight, filter.evaluate(0.3 * d, d));
+                assert!(weight <= peak);
+                if d > radius {
+                    assert_eq!(weight, 0.);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn weights_match_the_curves() {
+        assert_eq!(BoxFilter::default().evaluate(0.5, -0.5), 1.);
+        assert_eq!(TriangleFilter::new(2.).evaluate(1., 0.5), 1. * 1.5);
+        let gaussian = GaussianFilter::new(1.5, 2.);
+        let g = |d: f32| (-2. * d * d).exp() - (-4.5f32).exp();
+        assert!((gaussian.evaluate(0.5, 0.) - g(0.5) * g(0.)).abs() < 1e-6);
+        assert!(LanczosFilter::default().evaluate(1., 0.).abs() < 1e-6);
+    }
+
+    /// Sample weights of a pixel row do not depend on where in its pixel the sample fell, so
+    /// flat regions stay flat.
+    #[test]
+    fn mitchell_shifts_sum_to_a_constant() {
+        let mitchell = MitchellFilter::default();
+        let row = |x: f32| {
+            (-2..=2)
+                .map(|n| mitchell.evaluate(x + n as f32, 0.))
+                .sum::<f32>()
+        };
+        for i in 0..10 {
+            let x = i as f32 / 10.;
+            assert!((row(x) - row(0.)).abs() < 1e-5, "{} sums to {}", x, row(x));
+        }
+    }
+}
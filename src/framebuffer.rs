@@ -0,0 +1,112 @@
+use crate::{tile::Tile, tonemap::DisplayTransform};
+use anyhow::Result;
+use glam::Vec3A;
+use std::io::Write;
+
+pub struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Vec3A>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Vec3A::ZERO; width * height],
+        }
+    }
+
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Vec3A>) -> Self {
+        assert_eq!(pixels.len(), width * height, "pixel count mismatch");
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Pixel at column `x` of row `y`, with row 0 at the top of the image.
+    #[inline(always)]
+    pub fn get(&self, x: usize, y: usize) -> Vec3A {
+        self.pixels[y * self.width + x]
+    }
+
+    #[inline(always)]
+    pub fn set(&mut self, x: usize, y: usize, color: Vec3A) {
+        self.pixels[y * self.width + x] = color;
+    }
+
+    /// Copies the row-major `pixels` of `tile` into place.
+    pub fn write_tile(&mut self, tile: &Tile, pixels: &[Vec3A]) {
+        for (row, line) in pixels.chunks(tile.width).enumerate() {
+            let start = (tile.y + row) * self.width + tile.x;
+            self.pixels[start..start + tile.width].copy_from_slice(line);
+        }
+    }
+
+    /// The pixels of `tile` as an image of their own.
+    pub fn crop(&self, tile: &Tile) -> Framebuffer {
+        let pixels = tile.pixels().map(|(x, y)| self.get(x, y)).collect();
+        Framebuffer::from_pixels(tile.width, tile.height, pixels)
+    }
+
+    /// A black `width` x `height` image with this one placed at `tile`; the reverse of
+    /// [`Self::crop`].
+    pub fn uncrop(&self, tile: &Tile, width: usize, height: usize) -> Framebuffer {
+        let mut frame = Framebuffer::new(width, height);
+        frame.write_tile(tile, &self.pixels);
+        frame
+    }
+
+    /// Tone maps, sRGB encodes and quantizes the linear radiance into interleaved RGB bytes.
+    pub fn to_rgb8(&self, display: &DisplayTransform) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|c| {
+                display
+                    .apply(*c)
+                    .to_array()
+                    .map(|c| (256.0 * c.clamp(0.0, 0.999)) as u8)
+            })
+            .collect()
+    }
+
+    pub fn to_rgb16(&self, display: &DisplayTransform) -> Vec<u16> {
+        self.pixels
+            .iter()
+            .flat_map(|c| {
+                display
+                    .apply(*c)
+                    .to_array()
+                    .map(|c| (65536.0 * c.clamp(0.0, 0.99999)) as u16)
+            })
+            .collect()
+    }
+
+    /// Interleaved linear RGB floats, as stored.
+    pub fn to_rgb32f(&self) -> Vec<f32> {
+        self.pixels.iter().flat_map(|c| c.to_array()).collect()
+    }
+
+    pub fn write_ppm<W: Write>(&self, writer: &mut W, display: &DisplayTransform) -> Result<()> {
+        writeln!(writer, "P3\n{} {}\n255", self.width, self.height)?;
+        for col in self.to_rgb8(display).chunks(3) {
+            writeln!(writer, "{} {} {}", col[0], col[1], col[2])?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_ppm_binary<W: Write>(
+        &self,
+        writer: &mut W,
+        display: &DisplayTransform,
+    ) -> Result<()> {
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+        writer.write_all(&self.to_rgb8(display))?;
+
+        Ok(())
+    }
+}
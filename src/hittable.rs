@@ -1,15 +1,25 @@
 pub mod aabb;
 pub mod boxtype;
 pub mod bvh;
+pub mod constant_medium;
 pub mod flip_face;
+pub mod grid_medium;
 pub mod hittable_list;
+pub mod light_list;
 pub mod moving_sphere;
+pub mod object_id;
 pub mod rect;
 pub mod rotate;
 pub mod sphere;
 pub mod translate;
 
-use crate::{hittable::aabb::AABB, material::Material, ray::Ray, Rand};
+use crate::{
+    hittable::{aabb::AABB, bvh::TraversalStats},
+    material::Material,
+    medium::MediumBoundary,
+    ray::Ray,
+    sampler::Sampler,
+};
 use glam::Vec3A;
 
 pub struct HitRecord<'a> {
@@ -20,17 +30,44 @@ pub struct HitRecord<'a> {
     pub v: f32,
     pub material: &'a dyn Material,
     pub front_face: bool,
+    /// Set by [`object_id::ObjectId`], 0 for untagged objects.
+    pub object_id: u32,
+    /// Set when the ray crosses the boundary of a medium rather than hitting a surface.
+    pub medium: Option<MediumBoundary<'a>>,
+}
+
+/// Point drawn uniformly from the surface of a hittable.
+pub struct SurfaceSample {
+    pub point: Vec3A,
+    pub normal: Vec3A,
+    /// Density with respect to surface area.
+    pub pdf: f32,
 }
 
 pub trait Hittable: Sync + Send {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord>;
     fn bounding_box(&self, time: (f32, f32)) -> Option<AABB>;
+    /// Same as `hit`, adding the work of any BVH traversal to `stats`. Only the debug views
+    /// call it, so counting costs nothing in a normal render.
+    fn hit_with_stats(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        _stats: &mut TraversalStats,
+    ) -> Option<HitRecord<'_>> {
+        self.hit(ray, t_min, t_max)
+    }
     fn pdf_value(&self, _origin: Vec3A, _v: Vec3A) -> f32 {
         0.
     }
-    fn random(&self, _origin: Vec3A, _rng: &mut Rand) -> Vec3A {
+    fn random(&self, _origin: Vec3A, _sampler: &mut dyn Sampler) -> Vec3A {
         Vec3A::new(1., 0., 0.)
     }
+    /// Samples a point on the surface to emit light from, `None` if the shape cannot.
+    fn sample_surface(&self, _sampler: &mut dyn Sampler) -> Option<SurfaceSample> {
+        None
+    }
 }
 
 pub fn get_face_normal(ray: &Ray, outward_normal: Vec3A) -> (bool, Vec3A) {
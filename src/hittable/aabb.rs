@@ -14,6 +14,11 @@ impl AABB {
     }
 
     pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+        self.interval(ray, t_min, t_max).is_some()
+    }
+
+    /// Part of `(t_min, t_max)` during which `ray` is inside the box.
+    pub fn interval(&self, ray: &Ray, mut t_min: f32, mut t_max: f32) -> Option<(f32, f32)> {
         for i in 0..3 {
             let inv_d = 1. / ray.direction[i];
             let mut t0 = (self.min[i] - ray.origin[i]) * inv_d;
@@ -21,14 +26,14 @@ impl AABB {
             if inv_d < 0. {
                 swap(&mut t0, &mut t1);
             }
-            let t_min = if t0 > t_min { t0 } else { t_min };
-            let t_max = if t1 < t_max { t1 } else { t_max };
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
             if t_max <= t_min {
-                return false;
+                return None;
             }
         }
 
-        true
+        Some((t_min, t_max))
     }
 
     pub fn surrounding_box(box0: &AABB, box1: &AABB) -> Self {
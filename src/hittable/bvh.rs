@@ -5,16 +5,25 @@ use crate::{ray::Ray, Rand};
 use core::cmp::Ordering;
 use std::fmt;
 
-pub struct BvhTree<'a> {
-    nodes: Vec<BvhNode<'a>>,
+pub struct BvhTree {
+    nodes: Vec<BvhNode>,
     root: NodeId,
 }
 
-struct BvhNode<'a> {
+struct BvhNode {
     left: Option<NodeId>,
     right: Option<NodeId>,
     aabb: Option<AABB>,
-    hittable: Option<&'a Box<dyn Hittable>>,
+    hittable: Option<Box<dyn Hittable>>,
+}
+
+/// Work done by a [`BvhTree`] traversal, see [`Hittable::hit_with_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TraversalStats {
+    /// Nodes whose bounding box the ray entered.
+    pub node_visits: u32,
+    /// Bounding boxes tested against the ray, including the ones it missed.
+    pub aabb_tests: u32,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -22,7 +31,7 @@ pub struct NodeId {
     index: usize,
 }
 
-impl<'a> BvhTree<'a> {
+impl BvhTree {
     fn hit(&self, id: NodeId, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
         let node = &self.nodes[id.index];
 
@@ -65,9 +74,42 @@ impl<'a> BvhTree<'a> {
 
         None
     }
+
+    /// Same traversal as [`BvhTree::hit`], counting its work into `stats`.
+    fn hit_with_stats(
+        &self,
+        id: NodeId,
+        r: &Ray,
+        t_min: f32,
+        t_max: f32,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord<'_>> {
+        let node = &self.nodes[id.index];
+        if let Some(aabb) = &node.aabb {
+            stats.aabb_tests += 1;
+            if !aabb.hit(r, t_min, t_max) {
+                return None;
+            }
+        }
+        stats.node_visits += 1;
+
+        if let Some(hittable) = &node.hittable {
+            return hittable.hit_with_stats(r, t_min, t_max, stats);
+        }
+        let left = node
+            .left
+            .and_then(|left| self.hit_with_stats(left, r, t_min, t_max, stats));
+        let right = node
+            .right
+            .and_then(|right| self.hit_with_stats(right, r, t_min, t_max, stats));
+        match (left, right) {
+            (Some(left), Some(right)) => Some(if left.t < right.t { left } else { right }),
+            (left, right) => left.or(right),
+        }
+    }
 }
 
-impl<'a> Hittable for BvhTree<'a> {
+impl Hittable for BvhTree {
     fn bounding_box(&self, _: (f32, f32)) -> Option<AABB> {
         self.nodes[self.root.index].aabb
     }
@@ -75,10 +117,20 @@ impl<'a> Hittable for BvhTree<'a> {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
         self.hit(self.root, ray, t_min, t_max)
     }
+
+    fn hit_with_stats(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord<'_>> {
+        self.hit_with_stats(self.root, ray, t_min, t_max, stats)
+    }
 }
 
-impl<'a> BvhTree<'a> {
-    pub fn new(l: &'a mut [Box<dyn Hittable>], time: (f32, f32), rng: &mut Rand) -> BvhTree<'a> {
+impl BvhTree {
+    pub fn new(l: Vec<Box<dyn Hittable>>, time: (f32, f32), rng: &mut Rand) -> BvhTree {
         let mut tree = BvhTree {
             nodes: Vec::new(),
             root: NodeId { index: 0 },
@@ -88,12 +140,7 @@ impl<'a> BvhTree<'a> {
         tree
     }
 
-    fn build(
-        &mut self,
-        l: &'a mut [Box<dyn Hittable>],
-        time: (f32, f32),
-        rng: &mut Rand,
-    ) -> NodeId {
+    fn build(&mut self, mut l: Vec<Box<dyn Hittable>>, time: (f32, f32), rng: &mut Rand) -> NodeId {
         let axis: i32 = rng.gen_range(0..3);
 
         match axis {
@@ -107,13 +154,15 @@ impl<'a> BvhTree<'a> {
         let right: NodeId;
 
         if l.len() == 1 {
-            return self.new_leaf(&l[0], time);
+            return self.new_leaf(l.pop().unwrap(), time);
         } else if l.len() == 2 {
-            left = self.new_leaf(&l[0], time);
-            right = self.new_leaf(&l[1], time);
+            let second = l.pop().unwrap();
+            left = self.new_leaf(l.pop().unwrap(), time);
+            right = self.new_leaf(second, time);
         } else {
             let half_len = l.len() / 2;
-            let (left_hittables, right_hittables) = l.split_at_mut(half_len);
+            let right_hittables = l.split_off(half_len);
+            let left_hittables = l;
 
             left = self.build(left_hittables, time, rng);
             right = self.build(right_hittables, time, rng);
@@ -132,7 +181,7 @@ impl<'a> BvhTree<'a> {
         panic!("No bounding box in BvhNode::build");
     }
 
-    fn new_leaf(&mut self, hittable: &'a Box<dyn Hittable>, time: (f32, f32)) -> NodeId {
+    fn new_leaf(&mut self, hittable: Box<dyn Hittable>, time: (f32, f32)) -> NodeId {
         let next_index = self.nodes.len();
 
         self.nodes.push(BvhNode {
@@ -176,7 +225,7 @@ impl<'a> BvhTree<'a> {
     }
 }
 
-impl<'a> fmt::Display for BvhTree<'a> {
+impl fmt::Display for BvhTree {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
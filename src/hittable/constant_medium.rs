@@ -0,0 +1,130 @@
+use super::{aabb::AABB, HitRecord, Hittable};
+use crate::{
+    material::Material,
+    medium::{Medium, MediumBoundary},
+    ray::Ray,
+    sampler::Sampler,
+};
+use std::sync::Arc;
+
+/// Medium of uniform density filling a closed `boundary`. Rays report where they cross the
+/// boundary; the integrator samples where they scatter in between.
+pub struct ConstantMedium<H: Hittable, M: Material> {
+    boundary: Arc<H>,
+    /// Extinction coefficient, per unit of distance.
+    density: f32,
+    phase: Arc<M>,
+}
+
+impl<H: Hittable, M: Material> ConstantMedium<H, M> {
+    pub fn new(boundary: Arc<H>, density: f32, phase: Arc<M>) -> Self {
+        Self {
+            boundary,
+            density,
+            phase,
+        }
+    }
+}
+
+impl<H: Hittable, M: Material> Hittable for ConstantMedium<H, M> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let entry = self.boundary.hit(ray, f32::NEG_INFINITY, f32::INFINITY)?;
+        let exit = self.boundary.hit(ray, entry.t + 0.0001, f32::INFINITY)?;
+
+        let (crossing, entering) = if entry.t > t_min {
+            (entry, true)
+        } else if exit.t > t_min {
+            (exit, false)
+        } else {
+            return None;
+        };
+        if crossing.t >= t_max {
+            return None;
+        }
+
+        Some(HitRecord {
+            material: self.phase.as_ref(),
+            object_id: 0,
+            medium: Some(MediumBoundary {
+                medium: self,
+                entering,
+            }),
+            ..crossing
+        })
+    }
+
+    fn bounding_box(&self, time: (f32, f32)) -> Option<AABB> {
+        self.boundary.bounding_box(time)
+    }
+}
+
+impl<H: Hittable, M: Material> Medium for ConstantMedium<H, M> {
+    fn sample_distance(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        sampler: &mut dyn Sampler,
+    ) -> Option<f32> {
+        let distance = -(1. - sampler.get_1d()).ln() / self.density;
+        let t = t_min + distance / ray.direction.length();
+        (t < t_max).then_some(t)
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32, _sampler: &mut dyn Sampler) -> f32 {
+        (-self.density * (t_max - t_min) * ray.direction.length()).exp()
+    }
+
+    fn phase(&self) -> &dyn Material {
+        self.phase.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hittable::sphere::Sphere, material::isotropic::Isotropic, sampler::SamplerKind,
+        texture::solid_color::SolidColor,
+    };
+    use glam::Vec3A;
+
+    type Phase = Isotropic<SolidColor>;
+
+    fn fog(density: f32) -> ConstantMedium<Sphere<Phase>, Phase> {
+        let phase = Arc::new(Isotropic::from(Vec3A::ONE));
+        let boundary = Arc::new(Sphere::new(Vec3A::ZERO, 10., phase.clone()));
+        ConstantMedium::new(boundary, density, phase)
+    }
+
+    #[test]
+    fn transmittance_follows_beer_lambert() {
+        let medium = fog(0.7);
+        // The direction is not normalized, so the parameter range covers a distance of 3.
+        let ray = Ray::new(Vec3A::ZERO, Vec3A::new(0., 2., 0.), 0.);
+        let mut sampler = SamplerKind::Independent.sampler(0, 0, 0, 1);
+        let transmittance = medium.transmittance(&ray, 0.25, 1.75, sampler.as_mut());
+        assert!((transmittance - (-0.7f32 * 3.).exp()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn free_flights_escape_with_the_transmittance() {
+        let medium = fog(0.7);
+        let ray = Ray::new(Vec3A::ZERO, Vec3A::new(0., 2., 0.), 0.);
+        let count = 20000;
+        let mut escaped = 0;
+        for sample in 0..count {
+            let mut sampler = SamplerKind::Independent.sampler(0, 0, sample, 1);
+            match medium.sample_distance(&ray, 0.25, 1.75, sampler.as_mut()) {
+                Some(t) => assert!((0.25..1.75).contains(&t)),
+                None => escaped += 1,
+            }
+        }
+        let fraction = escaped as f32 / count as f32;
+        assert!(
+            (fraction - (-0.7f32 * 3.).exp()).abs() < 0.01,
+            "{}",
+            fraction
+        );
+    }
+}
@@ -1,5 +1,6 @@
-use super::{aabb::AABB, HitRecord, Hittable};
-use crate::ray::Ray;
+use super::{aabb::AABB, bvh::TraversalStats, HitRecord, Hittable, SurfaceSample};
+use crate::{ray::Ray, sampler::Sampler};
+use glam::Vec3A;
 use std::sync::Arc;
 
 pub struct FlipFace<H: Hittable> {
@@ -22,7 +23,31 @@ impl<H: Hittable> Hittable for FlipFace<H> {
         }
     }
 
+    fn hit_with_stats(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord<'_>> {
+        let mut hit = self.hittable.hit_with_stats(ray, t_min, t_max, stats)?;
+        hit.front_face = !hit.front_face;
+        Some(hit)
+    }
+
     fn bounding_box(&self, time: (f32, f32)) -> Option<AABB> {
         self.hittable.bounding_box(time)
     }
+
+    fn pdf_value(&self, origin: Vec3A, v: Vec3A) -> f32 {
+        self.hittable.pdf_value(origin, v)
+    }
+
+    fn random(&self, origin: Vec3A, sampler: &mut dyn Sampler) -> Vec3A {
+        self.hittable.random(origin, sampler)
+    }
+
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<SurfaceSample> {
+        self.hittable.sample_surface(sampler)
+    }
 }
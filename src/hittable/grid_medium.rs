@@ -0,0 +1,263 @@
+use super::{aabb::AABB, HitRecord, Hittable};
+use crate::{
+    material::Material,
+    medium::{Medium, MediumBoundary},
+    ray::Ray,
+    sampler::Sampler,
+};
+use anyhow::{ensure, Result};
+use glam::Vec3A;
+use std::{fs, path::Path, sync::Arc};
+
+/// Densities sampled on a regular voxel grid, x varying fastest, then y, then z.
+pub struct DensityGrid {
+    pub resolution: [usize; 3],
+    pub values: Vec<f32>,
+}
+
+impl DensityGrid {
+    pub fn new(resolution: [usize; 3], values: Vec<f32>) -> Self {
+        assert_eq!(values.len(), resolution.iter().product::<usize>());
+        Self { resolution, values }
+    }
+
+    /// Reads a headerless raw file of little-endian `f32` densities laid out as in [`Self::new`].
+    pub fn load<P: AsRef<Path>>(path: P, resolution: [usize; 3]) -> Result<Self> {
+        let bytes = fs::read(path.as_ref())?;
+        let count = resolution.iter().product::<usize>();
+        ensure!(
+            count > 0 && bytes.len() == count * 4,
+            "{} holds {} bytes but a {}x{}x{} grid needs {}",
+            path.as_ref().display(),
+            bytes.len(),
+            resolution[0],
+            resolution[1],
+            resolution[2],
+            count * 4
+        );
+
+        let values = bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        Ok(Self { resolution, values })
+    }
+
+    fn voxel(&self, x: usize, y: usize, z: usize) -> f32 {
+        let [nx, ny, _] = self.resolution;
+        self.values[(z * ny + y) * nx + x]
+    }
+
+    /// Trilinear interpolation at `p` in grid space, where voxel `(i, j, k)` is centred at
+    /// `(i + 0.5, j + 0.5, k + 0.5)`. Outside the grid the closest voxels are repeated.
+    pub fn lookup(&self, p: Vec3A) -> f32 {
+        let p = p - Vec3A::splat(0.5);
+        let base = p.floor();
+        let frac = p - base;
+        let index = |axis: usize, offset: f32| {
+            (base[axis] + offset).clamp(0., (self.resolution[axis] - 1) as f32) as usize
+        };
+
+        let mut density = 0.;
+        for corner in 0..8 {
+            let offset = Vec3A::new(
+                (corner & 1) as f32,
+                (corner >> 1 & 1) as f32,
+                (corner >> 2 & 1) as f32,
+            );
+            let weight = Vec3A::select(offset.cmpgt(Vec3A::ZERO), frac, Vec3A::ONE - frac);
+            density += weight.x
+                * weight.y
+                * weight.z
+                * self.voxel(index(0, offset.x), index(1, offset.y), index(2, offset.z));
+        }
+        density
+    }
+
+    pub fn max(&self) -> f32 {
+        self.values.iter().copied().fold(0., f32::max)
+    }
+}
+
+/// Medium whose density comes from a [`DensityGrid`] stretched over `bounds`. Free flights are
+/// sampled with delta tracking and transmittance is estimated with ratio tracking, both against
+/// the largest density in the grid.
+pub struct GridMedium<M: Material> {
+    bounds: AABB,
+    grid: DensityGrid,
+    /// Multiplies the grid values to give the extinction coefficient per unit of distance.
+    density_scale: f32,
+    max_density: f32,
+    phase: Arc<M>,
+}
+
+impl<M: Material> GridMedium<M> {
+    pub fn new(bounds: AABB, grid: DensityGrid, density_scale: f32, phase: Arc<M>) -> Self {
+        let max_density = grid.max() * density_scale;
+        Self {
+            bounds,
+            grid,
+            density_scale,
+            max_density,
+            phase,
+        }
+    }
+
+    pub fn density(&self, point: Vec3A) -> f32 {
+        let local = (point - self.bounds.min) / (self.bounds.max - self.bounds.min);
+        let resolution = Vec3A::new(
+            self.grid.resolution[0] as f32,
+            self.grid.resolution[1] as f32,
+            self.grid.resolution[2] as f32,
+        );
+        self.grid.lookup(local * resolution) * self.density_scale
+    }
+
+    /// Walks tentative collisions along `ray` through the part of `(t_min, t_max)` inside the
+    /// bounds, calling `collide` with each until it returns `false`. Returns the parameter of the
+    /// last collision, `None` if the walk left the interval.
+    fn track(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        sampler: &mut dyn Sampler,
+        mut collide: impl FnMut(f32, &mut dyn Sampler) -> bool,
+    ) -> Option<f32> {
+        if self.max_density <= 0. {
+            return None;
+        }
+        let (mut t, t_max) = self.bounds.interval(ray, t_min, t_max)?;
+        let step = 1. / (self.max_density * ray.direction.length());
+        loop {
+            t -= (1. - sampler.get_1d()).ln() * step;
+            if t >= t_max {
+                return None;
+            }
+            if !collide(t, sampler) {
+                return Some(t);
+            }
+        }
+    }
+}
+
+impl<M: Material> Hittable for GridMedium<M> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let (entry, exit) = self
+            .bounds
+            .interval(ray, f32::NEG_INFINITY, f32::INFINITY)?;
+
+        let (t, entering) = if entry > t_min {
+            (entry, true)
+        } else if exit > t_min {
+            (exit, false)
+        } else {
+            return None;
+        };
+        if t >= t_max {
+            return None;
+        }
+
+        Some(HitRecord {
+            point: ray.at(t),
+            normal: -ray.direction.normalize(),
+            t,
+            u: 0.,
+            v: 0.,
+            material: self.phase.as_ref(),
+            front_face: true,
+            object_id: 0,
+            medium: Some(MediumBoundary {
+                medium: self,
+                entering,
+            }),
+        })
+    }
+
+    fn bounding_box(&self, _time: (f32, f32)) -> Option<AABB> {
+        Some(self.bounds)
+    }
+}
+
+impl<M: Material> Medium for GridMedium<M> {
+    /// Delta tracking: a tentative collision is real with probability density / max density.
+    fn sample_distance(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        sampler: &mut dyn Sampler,
+    ) -> Option<f32> {
+        self.track(ray, t_min, t_max, sampler, |t, sampler| {
+            sampler.get_1d() * self.max_density >= self.density(ray.at(t))
+        })
+    }
+
+    /// Ratio tracking: every tentative collision scales the estimate by the chance it is null.
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut dyn Sampler) -> f32 {
+        let mut transmittance = 1.;
+        self.track(ray, t_min, t_max, sampler, |t, _| {
+            transmittance *= 1. - self.density(ray.at(t)) / self.max_density;
+            true
+        });
+        transmittance
+    }
+
+    fn phase(&self) -> &dyn Material {
+        self.phase.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        material::isotropic::Isotropic, sampler::SamplerKind, texture::solid_color::SolidColor,
+    };
+
+    /// Density 0.25 over the left quarter of a 2x1x1 box, rising linearly to 0.75 over the
+    /// middle half and staying there over the right quarter. A ray along x through all of it
+    /// sees an optical depth of 1.
+    fn ramp() -> GridMedium<Isotropic<SolidColor>> {
+        GridMedium::new(
+            AABB::new(Vec3A::ZERO, Vec3A::new(2., 1., 1.)),
+            DensityGrid::new([2, 1, 1], vec![1., 3.]),
+            0.25,
+            Arc::new(Isotropic::from(Vec3A::ONE)),
+        )
+    }
+
+    fn ray() -> Ray {
+        Ray::new(Vec3A::new(-1., 0.5, 0.5), Vec3A::X, 0.)
+    }
+
+    #[test]
+    fn ratio_tracking_averages_to_the_transmittance() {
+        let medium = ramp();
+        let count = 20000;
+        let mean = (0..count)
+            .map(|sample| {
+                let mut sampler = SamplerKind::Independent.sampler(0, 0, sample, 1);
+                medium.transmittance(&ray(), 0., 4., sampler.as_mut())
+            })
+            .sum::<f32>()
+            / count as f32;
+        assert!((mean - (-1f32).exp()).abs() < 0.01, "{}", mean);
+    }
+
+    #[test]
+    fn delta_tracking_escapes_with_the_transmittance() {
+        let medium = ramp();
+        let count = 20000;
+        let mut escaped = 0;
+        for sample in 0..count {
+            let mut sampler = SamplerKind::Independent.sampler(1, 0, sample, 1);
+            match medium.sample_distance(&ray(), 0., 4., sampler.as_mut()) {
+                Some(t) => assert!((1. ..3.).contains(&t)),
+                None => escaped += 1,
+            }
+        }
+        let fraction = escaped as f32 / count as f32;
+        assert!((fraction - (-1f32).exp()).abs() < 0.01, "{}", fraction);
+    }
+}
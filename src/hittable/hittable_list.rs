@@ -1,6 +1,7 @@
-use super::{HitRecord, Hittable};
+use super::{bvh::TraversalStats, object_id::ObjectId, HitRecord, Hittable, SurfaceSample};
 use crate::hittable::aabb::AABB;
-use crate::ray::Ray;
+use crate::{ray::Ray, sampler::Sampler};
+use glam::Vec3A;
 
 #[derive(Default)]
 pub struct HittableList {
@@ -11,6 +12,18 @@ impl HittableList {
     pub fn push(&mut self, hittable: impl Hittable + 'static) {
         self.objects.push(Box::new(hittable))
     }
+
+    /// Tags every member with its 1-based index for the object ID pass; 0 is left to the
+    /// background.
+    pub fn with_object_ids(self) -> Self {
+        let objects = self
+            .objects
+            .into_iter()
+            .enumerate()
+            .map(|(i, object)| Box::new(ObjectId::new(i as u32 + 1, object)) as Box<dyn Hittable>)
+            .collect();
+        Self { objects }
+    }
 }
 
 impl Hittable for HittableList {
@@ -28,6 +41,26 @@ impl Hittable for HittableList {
         hit_anything
     }
 
+    fn hit_with_stats(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord<'_>> {
+        let mut closest_so_far = t_max;
+        let mut hit_anything = None;
+
+        for object in &self.objects {
+            if let Some(hit) = object.hit_with_stats(ray, t_min, closest_so_far, stats) {
+                closest_so_far = hit.t;
+                hit_anything = Some(hit);
+            }
+        }
+
+        hit_anything
+    }
+
     fn bounding_box(&self, time: (f32, f32)) -> Option<AABB> {
         let mut output_box = None;
         for obj in self.objects.iter() {
@@ -43,4 +76,37 @@ impl Hittable for HittableList {
 
         output_box
     }
+
+    /// Mixture of the members' densities, each light being picked with equal probability.
+    fn pdf_value(&self, origin: Vec3A, v: Vec3A) -> f32 {
+        if self.objects.is_empty() {
+            return 0.;
+        }
+
+        let weight = 1. / self.objects.len() as f32;
+        self.objects
+            .iter()
+            .map(|object| weight * object.pdf_value(origin, v))
+            .sum()
+    }
+
+    fn random(&self, origin: Vec3A, sampler: &mut dyn Sampler) -> Vec3A {
+        if self.objects.is_empty() {
+            return Vec3A::new(1., 0., 0.);
+        }
+
+        let index = (sampler.get_1d() * self.objects.len() as f32) as usize;
+        self.objects[index.min(self.objects.len() - 1)].random(origin, sampler)
+    }
+
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<SurfaceSample> {
+        if self.objects.is_empty() {
+            return None;
+        }
+
+        let index = (sampler.get_1d() * self.objects.len() as f32) as usize;
+        let mut sample = self.objects[index.min(self.objects.len() - 1)].sample_surface(sampler)?;
+        sample.pdf /= self.objects.len() as f32;
+        Some(sample)
+    }
 }
@@ -0,0 +1,158 @@
+use super::{
+    aabb::AABB, bvh::TraversalStats, hittable_list::HittableList, HitRecord, Hittable,
+    SurfaceSample,
+};
+use crate::{ray::Ray, sampler::Sampler};
+use glam::Vec3A;
+
+/// Emitters sampled in proportion to a per-light power, so bright lamps receive more shadow
+/// rays than dim ones.
+#[derive(Default)]
+pub struct LightList {
+    lights: HittableList,
+    cdf: Vec<f32>,
+}
+
+impl LightList {
+    /// Adds a light whose share of the light samples is proportional to `power`, for example
+    /// its emitted radiance times its area.
+    pub fn push(&mut self, light: impl Hittable + 'static, power: f32) {
+        assert!(power > 0., "light power must be positive");
+        let total = self.total_power();
+        self.lights.push(light);
+        self.cdf.push(total + power);
+    }
+
+    pub fn len(&self) -> usize {
+        self.cdf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cdf.is_empty()
+    }
+
+    fn total_power(&self) -> f32 {
+        self.cdf.last().copied().unwrap_or(0.)
+    }
+
+    fn probability(&self, index: usize) -> f32 {
+        let lower = if index == 0 { 0. } else { self.cdf[index - 1] };
+        (self.cdf[index] - lower) / self.total_power()
+    }
+
+    /// Index of the light selected by the uniform number `u`.
+    fn pick(&self, u: f32) -> usize {
+        let target = u * self.total_power();
+        self.cdf
+            .partition_point(|&c| c <= target)
+            .min(self.len() - 1)
+    }
+}
+
+impl Hittable for LightList {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        self.lights.hit(ray, t_min, t_max)
+    }
+
+    fn hit_with_stats(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord<'_>> {
+        self.lights.hit_with_stats(ray, t_min, t_max, stats)
+    }
+
+    fn bounding_box(&self, time: (f32, f32)) -> Option<AABB> {
+        self.lights.bounding_box(time)
+    }
+
+    fn pdf_value(&self, origin: Vec3A, v: Vec3A) -> f32 {
+        self.lights
+            .objects
+            .iter()
+            .enumerate()
+            .map(|(i, light)| self.probability(i) * light.pdf_value(origin, v))
+            .sum()
+    }
+
+    fn random(&self, origin: Vec3A, sampler: &mut dyn Sampler) -> Vec3A {
+        if self.is_empty() {
+            return Vec3A::new(1., 0., 0.);
+        }
+
+        let index = self.pick(sampler.get_1d());
+        self.lights.objects[index].random(origin, sampler)
+    }
+
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<SurfaceSample> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let index = self.pick(sampler.get_1d());
+        let mut sample = self.lights.objects[index].sample_surface(sampler)?;
+        sample.pdf *= self.probability(index);
+        Some(sample)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hittable::sphere::Sphere, material::diffuse_light::DiffuseLight, sampler::SamplerKind,
+    };
+    use std::{f32::consts::PI, sync::Arc};
+
+    /// A dim light to the left of the origin and one three times as bright to the right.
+    fn two_lights() -> LightList {
+        let light = Arc::new(DiffuseLight::from(Vec3A::ONE));
+        let mut lights = LightList::default();
+        lights.push(Sphere::new(-4. * Vec3A::X, 1., light.clone()), 1.);
+        lights.push(Sphere::new(4. * Vec3A::X, 1., light), 3.);
+        lights
+    }
+
+    #[test]
+    fn random_follows_the_power_cdf() {
+        let lights = two_lights();
+        let count = 20000;
+        let mut right = 0;
+        for sample in 0..count {
+            let mut sampler = SamplerKind::Independent.sampler(1, 0, sample, 1);
+            let direction = lights.random(Vec3A::ZERO, sampler.as_mut());
+            assert!(lights.pdf_value(Vec3A::ZERO, direction) > 0.);
+            if direction.x > 0. {
+                right += 1;
+            }
+        }
+        let share = right as f32 / count as f32;
+        assert!((share - 0.75).abs() < 0.02, "{}", share);
+    }
+
+    #[test]
+    fn pdf_value_is_the_density_of_random() {
+        let lights = two_lights();
+        // Each sphere subtends a cone with sin(theta_max) = 1/4.
+        let solid_angle = 2. * PI * (1. - (1f32 - 1. / 16.).sqrt());
+        let count = 20000;
+        let mut inverse_pdf = 0.;
+        for sample in 0..count {
+            let mut sampler = SamplerKind::Independent.sampler(2, 0, sample, 1);
+            let direction = lights.random(Vec3A::ZERO, sampler.as_mut());
+            let pdf = lights.pdf_value(Vec3A::ZERO, direction);
+            let expected = if direction.x > 0. { 0.75 } else { 0.25 } / solid_angle;
+            assert!((pdf - expected).abs() < 1e-3 * expected);
+            inverse_pdf += 1. / pdf;
+        }
+        // The mean of 1 / pdf over its own samples is the solid angle it covers.
+        let covered = inverse_pdf / count as f32;
+        assert!(
+            (covered / (2. * solid_angle) - 1.).abs() < 0.05,
+            "{}",
+            covered
+        );
+    }
+}
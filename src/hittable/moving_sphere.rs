@@ -1,6 +1,7 @@
-use super::{get_face_normal, HitRecord, Hittable};
+use super::{get_face_normal, sphere::random_to_sphere, HitRecord, Hittable, SurfaceSample};
 use crate::hittable::aabb::AABB;
 use crate::material::Material;
+use crate::{onb::Onb, ray::Ray, sampler::Sampler, vec::uniform_sphere};
 use glam::Vec3A;
 use std::{f32::consts::PI, sync::Arc};
 
@@ -62,6 +63,8 @@ impl<M: Material> Hittable for MovingSphere<M> {
                     v,
                     material: self.material.as_ref(),
                     front_face,
+                    object_id: 0,
+                    medium: None,
                 });
             }
 
@@ -79,6 +82,8 @@ impl<M: Material> Hittable for MovingSphere<M> {
                     v,
                     material: self.material.as_ref(),
                     front_face,
+                    object_id: 0,
+                    medium: None,
                 });
             }
         }
@@ -92,4 +97,42 @@ impl<M: Material> Hittable for MovingSphere<M> {
         let box2 = AABB::new(self.center(time.1) - radius, self.center(time.1) + radius);
         Some(AABB::surrounding_box(&box1, &box2))
     }
+
+    /// Light sampling, like `sample_surface`, sees the sphere where it is at time 0.
+    fn pdf_value(&self, origin: Vec3A, v: Vec3A) -> f32 {
+        if self
+            .hit(&Ray::new(origin, v, 0.), 0.001, f32::INFINITY)
+            .is_none()
+        {
+            return 0.;
+        }
+
+        let distance_squared = (self.center(0.) - origin).length_squared();
+        let cos_theta_max = (1. - self.radius * self.radius / distance_squared)
+            .max(0.)
+            .sqrt();
+        let solid_angle = 2. * PI * (1. - cos_theta_max);
+
+        1. / solid_angle
+    }
+
+    fn random(&self, origin: Vec3A, sampler: &mut dyn Sampler) -> Vec3A {
+        let direction = self.center(0.) - origin;
+        let distance_squared = direction.length_squared();
+        let cos_theta_max = (1. - self.radius * self.radius / distance_squared)
+            .max(0.)
+            .sqrt();
+        let uvw = Onb::build_from_w(&direction);
+        uvw.local(&random_to_sphere(cos_theta_max, sampler.get_2d()))
+    }
+
+    /// Samples the sphere where it is at time 0, the time photons are traced at.
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<SurfaceSample> {
+        let normal = uniform_sphere(sampler.get_2d());
+        Some(SurfaceSample {
+            point: self.center(0.) + self.radius * normal,
+            normal,
+            pdf: 1. / (4. * PI * self.radius * self.radius),
+        })
+    }
 }
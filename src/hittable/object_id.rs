@@ -0,0 +1,51 @@
+use super::{aabb::AABB, bvh::TraversalStats, HitRecord, Hittable, SurfaceSample};
+use crate::{ray::Ray, sampler::Sampler};
+use glam::Vec3A;
+
+/// Tags every hit on the wrapped object with `id`, for the object ID pass.
+pub struct ObjectId<H: Hittable + ?Sized> {
+    pub id: u32,
+    pub hittable: Box<H>,
+}
+
+impl<H: Hittable + ?Sized> ObjectId<H> {
+    pub fn new(id: u32, hittable: Box<H>) -> Self {
+        Self { id, hittable }
+    }
+}
+
+impl<H: Hittable + ?Sized> Hittable for ObjectId<H> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let mut hit = self.hittable.hit(ray, t_min, t_max)?;
+        hit.object_id = self.id;
+        Some(hit)
+    }
+
+    fn hit_with_stats(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord<'_>> {
+        let mut hit = self.hittable.hit_with_stats(ray, t_min, t_max, stats)?;
+        hit.object_id = self.id;
+        Some(hit)
+    }
+
+    fn bounding_box(&self, time: (f32, f32)) -> Option<AABB> {
+        self.hittable.bounding_box(time)
+    }
+
+    fn pdf_value(&self, origin: Vec3A, v: Vec3A) -> f32 {
+        self.hittable.pdf_value(origin, v)
+    }
+
+    fn random(&self, origin: Vec3A, sampler: &mut dyn Sampler) -> Vec3A {
+        self.hittable.random(origin, sampler)
+    }
+
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<SurfaceSample> {
+        self.hittable.sample_surface(sampler)
+    }
+}
@@ -1,7 +1,6 @@
-use super::{aabb::AABB, get_face_normal, HitRecord, Hittable};
-use crate::{material::Material, ray::Ray, Rand};
+use super::{aabb::AABB, get_face_normal, HitRecord, Hittable, SurfaceSample};
+use crate::{material::Material, ray::Ray, sampler::Sampler};
 use glam::Vec3A;
-use rand::Rng;
 use std::{f32::INFINITY, intrinsics::fabsf32, sync::Arc};
 
 pub enum Plane {
@@ -60,6 +59,8 @@ impl<M: Material> Hittable for AARect<M> {
                     normal,
                     material: self.material.as_ref(),
                     front_face,
+                    object_id: 0,
+                    medium: None,
                 })
             }
         }
@@ -90,12 +91,29 @@ impl<M: Material> Hittable for AARect<M> {
         }
     }
 
-    fn random(&self, origin: Vec3A, rng: &mut Rand) -> Vec3A {
-        let random_point = Vec3A::new(
-            rng.gen_range(self.a.0..self.a.1),
-            self.k,
-            rng.gen_range(self.b.0..self.b.1),
-        );
+    fn random(&self, origin: Vec3A, sampler: &mut dyn Sampler) -> Vec3A {
+        let u = sampler.get_2d();
+        let a = self.a.0 + u.x * (self.a.1 - self.a.0);
+        let b = self.b.0 + u.y * (self.b.1 - self.b.0);
+        let random_point = match &self.plane {
+            Plane::YZ => Vec3A::new(self.k, a, b),
+            Plane::XZ => Vec3A::new(a, self.k, b),
+            Plane::XY => Vec3A::new(a, b, self.k),
+        };
         random_point - origin
     }
+
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<SurfaceSample> {
+        let normal = match &self.plane {
+            Plane::YZ => Vec3A::X,
+            Plane::XZ => Vec3A::Y,
+            Plane::XY => Vec3A::Z,
+        };
+        let area = (self.a.1 - self.a.0) * (self.b.1 - self.b.0);
+        Some(SurfaceSample {
+            point: self.random(Vec3A::ZERO, sampler),
+            normal,
+            pdf: 1. / area,
+        })
+    }
 }
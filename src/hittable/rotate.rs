@@ -1,5 +1,5 @@
-use super::{aabb::AABB, HitRecord, Hittable};
-use crate::ray::Ray;
+use super::{aabb::AABB, bvh::TraversalStats, HitRecord, Hittable, SurfaceSample};
+use crate::{ray::Ray, sampler::Sampler};
 use glam::Vec3A;
 use std::{f32::consts::PI, sync::Arc};
 
@@ -81,36 +81,76 @@ impl<H: Hittable + Send + Sync> Rotate<H> {
     }
 }
 
+impl<H: Hittable + Send + Sync> Rotate<H> {
+    /// Rotates `v` from the object's frame into the world.
+    fn rotate(&self, v: Vec3A) -> Vec3A {
+        let (_, a_axis, b_axis) = get_axis(&self.axis);
+        let mut rotated = v;
+        rotated[a_axis] = self.cos_theta * v[a_axis] - self.sin_theta * v[b_axis];
+        rotated[b_axis] = self.sin_theta * v[a_axis] + self.cos_theta * v[b_axis];
+        rotated
+    }
+
+    /// Rotates `v` from the world into the object's frame.
+    fn unrotate(&self, v: Vec3A) -> Vec3A {
+        let (_, a_axis, b_axis) = get_axis(&self.axis);
+        let mut rotated = v;
+        rotated[a_axis] = self.cos_theta * v[a_axis] + self.sin_theta * v[b_axis];
+        rotated[b_axis] = -self.sin_theta * v[a_axis] + self.cos_theta * v[b_axis];
+        rotated
+    }
+
+    fn object_ray(&self, ray: &Ray) -> Ray {
+        Ray::new(
+            self.unrotate(ray.origin),
+            self.unrotate(ray.direction),
+            ray.time,
+        )
+    }
+
+    fn world_hit<'a>(&self, mut hit: HitRecord<'a>) -> HitRecord<'a> {
+        hit.point = self.rotate(hit.point);
+        hit.normal = self.rotate(hit.normal);
+        hit
+    }
+}
+
 impl<H: Hittable + Sync + Send> Hittable for Rotate<H> {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        let (_, a_axis, b_axis) = get_axis(&self.axis);
-        let mut origin = ray.origin;
-        let mut direction = ray.direction;
-        origin[a_axis] = self.cos_theta * ray.origin[a_axis] + self.sin_theta * ray.origin[b_axis];
-        origin[b_axis] = -self.sin_theta * ray.origin[a_axis] + self.cos_theta * ray.origin[b_axis];
-        direction[a_axis] =
-            self.cos_theta * ray.direction[a_axis] + self.sin_theta * ray.direction[b_axis];
-        direction[b_axis] =
-            -self.sin_theta * ray.direction[a_axis] + self.cos_theta * ray.direction[b_axis];
-        let rotated_ray = Ray::new(origin, direction, ray.time);
         self.hittable
-            .hit(&rotated_ray, t_min, t_max)
-            .map(|mut hit| {
-                let mut p = hit.point;
-                let mut normal = hit.normal;
-                p[a_axis] = self.cos_theta * hit.point[a_axis] - self.sin_theta * hit.point[b_axis];
-                p[b_axis] = self.sin_theta * hit.point[a_axis] + self.cos_theta * hit.point[b_axis];
-                normal[a_axis] =
-                    self.cos_theta * hit.normal[a_axis] - self.sin_theta * hit.normal[b_axis];
-                normal[b_axis] =
-                    self.sin_theta * hit.normal[a_axis] + self.cos_theta * hit.normal[b_axis];
-                hit.point = p;
-                hit.normal = normal;
-                hit
-            })
+            .hit(&self.object_ray(ray), t_min, t_max)
+            .map(|hit| self.world_hit(hit))
+    }
+
+    fn hit_with_stats(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord<'_>> {
+        self.hittable
+            .hit_with_stats(&self.object_ray(ray), t_min, t_max, stats)
+            .map(|hit| self.world_hit(hit))
     }
 
     fn bounding_box(&self, _: (f32, f32)) -> Option<AABB> {
         self.bbox.clone()
     }
+
+    fn pdf_value(&self, origin: Vec3A, v: Vec3A) -> f32 {
+        self.hittable
+            .pdf_value(self.unrotate(origin), self.unrotate(v))
+    }
+
+    fn random(&self, origin: Vec3A, sampler: &mut dyn Sampler) -> Vec3A {
+        self.rotate(self.hittable.random(self.unrotate(origin), sampler))
+    }
+
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<SurfaceSample> {
+        let mut sample = self.hittable.sample_surface(sampler)?;
+        sample.point = self.rotate(sample.point);
+        sample.normal = self.rotate(sample.normal);
+        Some(sample)
+    }
 }
@@ -1,6 +1,9 @@
-use super::{get_face_normal, HitRecord, Hittable};
-use crate::{hittable::aabb::AABB, material::Material, ray::Ray};
-use glam::Vec3A;
+use super::{get_face_normal, HitRecord, Hittable, SurfaceSample};
+use crate::{
+    hittable::aabb::AABB, material::Material, onb::Onb, ray::Ray, sampler::Sampler,
+    vec::uniform_sphere,
+};
+use glam::{Vec2, Vec3A};
 use std::{f32::consts::PI, sync::Arc};
 
 pub struct Sphere<M: Material> {
@@ -16,6 +19,16 @@ fn get_sphere_uv(p: &Vec3A) -> (f32, f32) {
     (phi / (2. * PI), theta / PI)
 }
 
+/// Uniform direction inside the cone of half-angle `acos(cos_theta_max)` around +z.
+pub(super) fn random_to_sphere(cos_theta_max: f32, u: Vec2) -> Vec3A {
+    let (r1, r2) = (u.x, u.y);
+    let z = 1. + r2 * (cos_theta_max - 1.);
+
+    let phi = 2. * PI * r1;
+    let sqrt_z = (1. - z * z).max(0.).sqrt();
+    Vec3A::new(phi.cos() * sqrt_z, phi.sin() * sqrt_z, z)
+}
+
 impl<M: Material> Sphere<M> {
     #[allow(dead_code)]
     pub fn new(center: Vec3A, radius: f32, material: Arc<M>) -> Self {
@@ -53,6 +66,8 @@ impl<M: Material> Hittable for Sphere<M> {
                     v,
                     material: self.material.as_ref(),
                     front_face,
+                    object_id: 0,
+                    medium: None,
                 });
             }
             let t = (-b + sqrt_discriminant) / a;
@@ -69,6 +84,8 @@ impl<M: Material> Hittable for Sphere<M> {
                     v,
                     material: self.material.as_ref(),
                     front_face,
+                    object_id: 0,
+                    medium: None,
                 });
             }
         }
@@ -80,4 +97,40 @@ impl<M: Material> Hittable for Sphere<M> {
         let radius = Vec3A::splat(self.radius);
         Some(AABB::new(self.center - radius, self.center + radius))
     }
+
+    fn pdf_value(&self, origin: Vec3A, v: Vec3A) -> f32 {
+        if self
+            .hit(&Ray::new(origin, v, 0.), 0.001, f32::INFINITY)
+            .is_none()
+        {
+            return 0.;
+        }
+
+        let distance_squared = (self.center - origin).length_squared();
+        let cos_theta_max = (1. - self.radius * self.radius / distance_squared)
+            .max(0.)
+            .sqrt();
+        let solid_angle = 2. * PI * (1. - cos_theta_max);
+
+        1. / solid_angle
+    }
+
+    fn random(&self, origin: Vec3A, sampler: &mut dyn Sampler) -> Vec3A {
+        let direction = self.center - origin;
+        let distance_squared = direction.length_squared();
+        let cos_theta_max = (1. - self.radius * self.radius / distance_squared)
+            .max(0.)
+            .sqrt();
+        let uvw = Onb::build_from_w(&direction);
+        uvw.local(&random_to_sphere(cos_theta_max, sampler.get_2d()))
+    }
+
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<SurfaceSample> {
+        let normal = uniform_sphere(sampler.get_2d());
+        Some(SurfaceSample {
+            point: self.center + self.radius * normal,
+            normal,
+            pdf: 1. / (4. * PI * self.radius * self.radius),
+        })
+    }
 }
@@ -2,9 +2,9 @@ use std::sync::Arc;
 
 use glam::Vec3A;
 
-use crate::ray::Ray;
+use crate::{ray::Ray, sampler::Sampler};
 
-use super::{get_face_normal, Hittable};
+use super::{bvh::TraversalStats, get_face_normal, HitRecord, Hittable, SurfaceSample};
 
 pub struct Translate<H: Hittable + Send> {
     pub hittable: Arc<H>,
@@ -16,19 +16,37 @@ impl<H: Hittable + Send> Translate<H> {
     pub fn new(hittable: Arc<H>, offset: Vec3A) -> Self {
         Self { hittable, offset }
     }
+
+    fn object_ray(&self, ray: &Ray) -> Ray {
+        Ray::new(ray.origin - self.offset, ray.direction, ray.time)
+    }
+
+    fn world_hit<'a>(&self, moved_ray: &Ray, mut hit: HitRecord<'a>) -> HitRecord<'a> {
+        hit.point += self.offset;
+        (hit.front_face, hit.normal) = get_face_normal(moved_ray, hit.normal);
+        hit
+    }
 }
 
 impl<H: Hittable + Send> Hittable for Translate<H> {
-    fn hit(&self, ray: &crate::ray::Ray, t_min: f32, t_max: f32) -> Option<super::HitRecord> {
-        let moved_ray = Ray::new(ray.origin - self.offset, ray.direction, ray.time);
-        if let Some(mut hit) = self.hittable.hit(&moved_ray, t_min, t_max) {
-            hit.point += self.offset;
-            (hit.front_face, hit.normal) = get_face_normal(&moved_ray, hit.normal);
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        let moved_ray = self.object_ray(ray);
+        self.hittable
+            .hit(&moved_ray, t_min, t_max)
+            .map(|hit| self.world_hit(&moved_ray, hit))
+    }
 
-            Some(hit)
-        } else {
-            None
-        }
+    fn hit_with_stats(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        stats: &mut TraversalStats,
+    ) -> Option<HitRecord<'_>> {
+        let moved_ray = self.object_ray(ray);
+        self.hittable
+            .hit_with_stats(&moved_ray, t_min, t_max, stats)
+            .map(|hit| self.world_hit(&moved_ray, hit))
     }
 
     fn bounding_box(&self, time: (f32, f32)) -> Option<super::aabb::AABB> {
@@ -41,4 +59,18 @@ impl<H: Hittable + Send> Hittable for Translate<H> {
             None
         }
     }
+
+    fn pdf_value(&self, origin: Vec3A, v: Vec3A) -> f32 {
+        self.hittable.pdf_value(origin - self.offset, v)
+    }
+
+    fn random(&self, origin: Vec3A, sampler: &mut dyn Sampler) -> Vec3A {
+        self.hittable.random(origin - self.offset, sampler)
+    }
+
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<SurfaceSample> {
+        let mut sample = self.hittable.sample_surface(sampler)?;
+        sample.point += self.offset;
+        Some(sample)
+    }
 }
@@ -0,0 +1,404 @@
+use crate::{
+    aov::AovSample,
+    hittable::{HitRecord, Hittable},
+    medium::Medium,
+    onb::Onb,
+    ray::Ray,
+    sampler::Sampler,
+    scene::Scene,
+    spectrum::SampledWavelengths,
+    vec::random_cosine_direction,
+    RenderSettings,
+};
+use glam::{Vec3A, Vec4};
+use std::{f32::consts::PI, ops::Mul};
+
+pub mod bdpt;
+pub mod debug;
+pub mod photon_map;
+
+use debug::DebugMode;
+use photon_map::PhotonMap;
+
+/// Light transport algorithm used to estimate the radiance of each camera ray.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Integrator {
+    /// Unidirectional path tracing with next-event estimation.
+    #[default]
+    PathTracer,
+    /// Bidirectional path tracing, see [`bdpt`]. Both subpaths pass straight through
+    /// participating media, so `ConstantMedium` and `GridMedium` are invisible to it, and it
+    /// cannot render spectrally.
+    Bidirectional,
+    /// Path tracing with caustics gathered from progressive photon maps, see [`photon_map`].
+    /// Photons start on the surfaces of `Scene::lights`, so only lights made of shapes that
+    /// implement `Hittable::sample_surface` (rectangles and spheres, possibly transformed)
+    /// emit any. Photons pass straight through participating media and carry RGB even in
+    /// spectral mode, so caustics are neither scattered by fog nor dispersed.
+    PhotonMapping,
+    /// False-colour view of the geometry or of the BVH traversal at the first hit.
+    Debug(DebugMode),
+}
+
+impl Integrator {
+    pub(crate) fn radiance(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        settings: &RenderSettings,
+        photons: Option<&PhotonMap>,
+        sampler: &mut dyn Sampler,
+        aov: &mut AovSample,
+    ) -> Vec3A {
+        let photons = match self {
+            Integrator::PathTracer => None,
+            Integrator::Bidirectional => {
+                return bdpt::ray_color(ray, scene, settings, sampler, aov)
+            }
+            Integrator::PhotonMapping => photons,
+            Integrator::Debug(mode) => return debug::ray_color(ray, scene, *mode, aov),
+        };
+
+        let wavelengths = settings
+            .spectral
+            .then(|| SampledWavelengths::sample(sampler.get_1d()));
+        let radiance = ray_color(ray, scene, settings, photons, wavelengths, sampler, aov);
+        match wavelengths {
+            Some(wavelengths) => wavelengths.to_rgb(radiance),
+            None => Vec3A::from(radiance),
+        }
+    }
+}
+
+/// Weighting used to combine light and BSDF samples with multiple importance sampling.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MisHeuristic {
+    Balance,
+    #[default]
+    Power,
+}
+
+impl MisHeuristic {
+    /// Weight of a sample drawn with density `pdf` when `other_pdf` could also have produced it.
+    pub fn weight(&self, pdf: f32, other_pdf: f32) -> f32 {
+        let (a, b) = match self {
+            MisHeuristic::Balance => (pdf, other_pdf),
+            MisHeuristic::Power => (pdf * pdf, other_pdf * other_pdf),
+        };
+        if a + b > 0. {
+            a / (a + b)
+        } else {
+            0.
+        }
+    }
+}
+
+/// Radiance arriving along `ray`. The surface the ray hits first is recorded in `aov`. With a
+/// photon map, caustics on diffuse surfaces come from the map instead of from the paths. The
+/// camera is assumed to be outside of every medium.
+///
+/// Radiance is carried in the lanes of a `Vec4`: red, green and blue with the last lane unused, or
+/// one lane per wavelength when `wavelengths` is set.
+pub(crate) fn ray_color(
+    ray: &Ray,
+    scene: &Scene,
+    settings: &RenderSettings,
+    photons: Option<&PhotonMap>,
+    mut wavelengths: Option<SampledWavelengths>,
+    sampler: &mut dyn Sampler,
+    aov: &mut AovSample,
+) -> Vec4 {
+    let mut radiance = Vec4::ZERO;
+    let mut throughput = Vec4::ONE;
+    let mut ray = Ray::new(ray.origin, ray.direction, ray.time);
+    // Density of the BSDF sample that produced `ray`, `None` for camera rays and specular bounces
+    // which the light sampling could not have generated.
+    let mut bsdf_pdf: Option<f32> = None;
+    // Whether the path went through a diffuse surface, past which the photon map has already
+    // accounted for light arriving through specular bounces.
+    let mut diffuse_seen = false;
+    let mut medium: Option<&dyn Medium> = None;
+
+    for depth in 0..settings.max_depth {
+        // Light reaching the camera after bouncing off two or more surfaces.
+        let indirect = depth >= 2;
+
+        let Some((hit, in_medium)) = next_event(scene, &ray, &mut medium, sampler) else {
+            let background = lift(scene.background, wavelengths.as_ref());
+            radiance += clamp_indirect(throughput * background, indirect, settings);
+            break;
+        };
+        if depth == 0 {
+            *aov = AovSample::new(&ray, &hit);
+        }
+
+        let emitted = hit.material.emitted(&ray, &hit);
+        let caustic = photons.is_some() && diffuse_seen && bsdf_pdf.is_none();
+        if emitted != Vec3A::ZERO && !caustic {
+            let weight = match bsdf_pdf {
+                Some(bsdf_pdf) => {
+                    let light_pdf = scene.lights.pdf_value(ray.origin, ray.direction);
+                    settings.mis_heuristic.weight(bsdf_pdf, light_pdf)
+                }
+                None => 1.,
+            };
+            let emitted = lift(emitted, wavelengths.as_ref());
+            radiance += clamp_indirect(weight * throughput * emitted, indirect, settings);
+        }
+
+        let srec = match &mut wavelengths {
+            Some(wavelengths) if hit.material.dispersive() => {
+                throughput *= wavelengths.terminate_secondary();
+                let hero = wavelengths.hero();
+                hit.material.sample_wavelength(&ray, &hit, hero, sampler)
+            }
+            _ => hit.material.sample(&ray, &hit, sampler),
+        };
+        let Some(srec) = srec else {
+            break;
+        };
+
+        if srec.specular {
+            bsdf_pdf = None;
+        } else {
+            let direct = throughput
+                * sample_light(
+                    &ray,
+                    &hit,
+                    scene,
+                    settings,
+                    medium,
+                    wavelengths.as_ref(),
+                    sampler,
+                );
+            radiance += clamp_indirect(direct, depth >= 1, settings);
+            // Photons only land on surfaces.
+            if let (Some(photons), false) = (photons, in_medium) {
+                let caustics =
+                    throughput * lift(photons.estimate(&ray, &hit), wavelengths.as_ref());
+                radiance += clamp_indirect(caustics, true, settings);
+            }
+            bsdf_pdf = Some(srec.pdf);
+            diffuse_seen |= !in_medium;
+        }
+        throughput *= lift(srec.attenuation, wavelengths.as_ref());
+
+        // Russian roulette: survive with a probability proportional to the throughput
+        // and reweight the survivors so the estimate stays unbiased.
+        if depth >= settings.rr_min_depth {
+            let survival = throughput.max_element().min(1.);
+            if sampler.get_1d() >= survival {
+                break;
+            }
+            throughput /= survival;
+        }
+
+        ray = Ray::new(hit.point, srec.direction, ray.time);
+    }
+
+    radiance
+}
+
+/// Converts an RGB quantity to the channels a path is traced in.
+fn lift(rgb: Vec3A, wavelengths: Option<&SampledWavelengths>) -> Vec4 {
+    match wavelengths {
+        Some(wavelengths) => wavelengths.upsample(rgb),
+        None => rgb.extend(0.),
+    }
+}
+
+/// Radiance in RGB or in sampled wavelengths.
+trait Channels: Copy + Mul<f32, Output = Self> {
+    fn peak(self) -> f32;
+}
+
+impl Channels for Vec3A {
+    fn peak(self) -> f32 {
+        self.max_element()
+    }
+}
+
+impl Channels for Vec4 {
+    fn peak(self) -> f32 {
+        self.max_element()
+    }
+}
+
+/// Scales an indirect contribution down so no channel exceeds `settings.max_radiance`, keeping
+/// its hue. Trades a little energy for the removal of fireflies.
+fn clamp_indirect<C: Channels>(contribution: C, indirect: bool, settings: &RenderSettings) -> C {
+    match settings.max_radiance {
+        Some(max_radiance) if indirect => {
+            let peak = contribution.peak();
+            if peak > max_radiance {
+                contribution * (max_radiance / peak)
+            } else {
+                contribution
+            }
+        }
+        _ => contribution,
+    }
+}
+
+/// Next-event estimation: samples a direction toward the lights, traces a shadow ray and
+/// returns the MIS-weighted direct lighting at `hit`, which lies in `medium`.
+fn sample_light(
+    ray: &Ray,
+    hit: &HitRecord,
+    scene: &Scene,
+    settings: &RenderSettings,
+    medium: Option<&dyn Medium>,
+    wavelengths: Option<&SampledWavelengths>,
+    sampler: &mut dyn Sampler,
+) -> Vec4 {
+    let direction = scene.lights.random(hit.point, sampler);
+    let light_pdf = scene.lights.pdf_value(hit.point, direction);
+    if light_pdf <= 0. {
+        return Vec4::ZERO;
+    }
+
+    let bsdf = hit.material.eval(ray, hit, direction);
+    if bsdf == Vec3A::ZERO {
+        return Vec4::ZERO;
+    }
+
+    let shadow_ray = Ray::new(hit.point, direction, ray.time);
+    match shadow_hit(scene, &shadow_ray, medium, sampler) {
+        Some((light_hit, transmittance)) => {
+            let emitted = light_hit.material.emitted(&shadow_ray, &light_hit);
+            let bsdf_pdf = hit.material.pdf(ray, hit, direction);
+            let weight = settings.mis_heuristic.weight(light_pdf, bsdf_pdf);
+            // Upsampled one by one: the spectrum of an RGB product is not the product of the spectra.
+            let bsdf = lift(bsdf, wavelengths);
+            let emitted = lift(emitted, wavelengths);
+            weight * transmittance * bsdf * emitted / light_pdf
+        }
+        None => Vec4::ZERO,
+    }
+}
+
+/// Follows `ray` to where it next scatters: a surface, or a free-flight sample inside the medium
+/// it travels through, flagged by the returned `bool`. Medium boundaries on the way update
+/// `medium`.
+fn next_event<'a>(
+    scene: &'a Scene,
+    ray: &Ray,
+    medium: &mut Option<&'a dyn Medium>,
+    sampler: &mut dyn Sampler,
+) -> Option<(HitRecord<'a>, bool)> {
+    let mut t_min = 0.001;
+    loop {
+        let hit = scene.world.hit(ray, t_min, f32::INFINITY);
+        if let Some(current) = *medium {
+            let t_max = hit.as_ref().map_or(f32::INFINITY, |hit| hit.t);
+            if let Some(t) = current.sample_distance(ray, t_min, t_max, sampler) {
+                let event = HitRecord {
+                    point: ray.at(t),
+                    normal: -ray.direction.normalize(),
+                    t,
+                    u: 0.,
+                    v: 0.,
+                    material: current.phase(),
+                    front_face: true,
+                    object_id: 0,
+                    medium: None,
+                };
+                return Some((event, true));
+            }
+        }
+
+        let hit = hit?;
+        let Some(boundary) = hit.medium else {
+            return Some((hit, false));
+        };
+        *medium = boundary.entering.then_some(boundary.medium);
+        t_min = hit.t;
+    }
+}
+
+/// First surface along `ray`, which starts in `medium`, together with the fraction of light
+/// that makes it through the media on the way.
+fn shadow_hit<'a>(
+    scene: &'a Scene,
+    ray: &Ray,
+    mut medium: Option<&'a dyn Medium>,
+    sampler: &mut dyn Sampler,
+) -> Option<(HitRecord<'a>, f32)> {
+    let mut t_min = 0.001;
+    let mut transmittance = 1.;
+    loop {
+        let hit = scene.world.hit(ray, t_min, f32::INFINITY);
+        if let Some(current) = medium {
+            let t_max = hit.as_ref().map_or(f32::INFINITY, |hit| hit.t);
+            transmittance *= current.transmittance(ray, t_min, t_max, sampler);
+        }
+
+        let hit = hit?;
+        let Some(boundary) = hit.medium else {
+            return Some((hit, transmittance));
+        };
+        medium = boundary.entering.then_some(boundary.medium);
+        t_min = hit.t;
+    }
+}
+
+/// First surface along `ray` within `(t_min, t_max)`, looking straight through media. Used by
+/// the integrators that do not simulate participating media.
+pub(crate) fn surface_hit<'a>(
+    world: &'a dyn Hittable,
+    ray: &Ray,
+    mut t_min: f32,
+    t_max: f32,
+) -> Option<HitRecord<'a>> {
+    loop {
+        let hit = world.hit(ray, t_min, t_max)?;
+        if hit.medium.is_none() {
+            return Some(hit);
+        }
+        t_min = hit.t;
+    }
+}
+
+/// Light leaving a point on an emitter, see [`sample_emission`].
+pub(crate) struct Emission<'a> {
+    pub ray: Ray,
+    /// Solid angle density of `ray.direction`.
+    pub pdf: f32,
+    /// Radiance carried along `ray`, never zero.
+    pub radiance: Vec3A,
+    /// First surface `ray` reaches.
+    pub receiver: HitRecord<'a>,
+}
+
+/// Sends light from `point` on an emitter with surface normal `normal` in a cosine-distributed
+/// direction on either side of it. The emitted radiance is looked up from the receiving end, so
+/// flipped faces are honoured and one-sided lights return nothing on their back. `None` if the
+/// ray leaves the scene or carries no light.
+pub(crate) fn sample_emission<'a>(
+    world: &'a dyn Hittable,
+    point: Vec3A,
+    normal: Vec3A,
+    time: f32,
+    sampler: &mut dyn Sampler,
+) -> Option<Emission<'a>> {
+    let side = if sampler.get_1d() < 0.5 { 1. } else { -1. };
+    let uvw = Onb::build_from_w(&(side * normal));
+    let direction = uvw
+        .local(&random_cosine_direction(sampler.get_2d()))
+        .normalize();
+    let ray = Ray::new(point, direction, time);
+    let receiver = surface_hit(world, &ray, 0.001, f32::INFINITY)?;
+
+    let back = Ray::new(receiver.point, point - receiver.point, time);
+    let emitter = surface_hit(world, &back, 0.001, f32::INFINITY)?;
+    let radiance = emitter.material.emitted(&back, &emitter);
+    if radiance == Vec3A::ZERO {
+        return None;
+    }
+    Some(Emission {
+        ray,
+        pdf: 0.5 * normal.dot(direction).abs() / PI,
+        radiance,
+        receiver,
+    })
+}
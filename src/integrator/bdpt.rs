@@ -0,0 +1,388 @@
+use super::{clamp_indirect, sample_emission, surface_hit, MisHeuristic};
+use crate::{
+    aov::AovSample, hittable::HitRecord, ray::Ray, sampler::Sampler, scene::Scene, RenderSettings,
+};
+use glam::Vec3A;
+use std::f32::consts::PI;
+
+/// A point on a camera or light subpath.
+struct Vertex<'a> {
+    point: Vec3A,
+    normal: Vec3A,
+    /// `None` for the camera and for the point sampled on a light.
+    hit: Option<HitRecord<'a>>,
+    /// Ray the subpath arrived along.
+    ray: Ray,
+    /// Throughput of the subpath up to this vertex.
+    beta: Vec3A,
+    /// The subpath left this vertex through a delta lobe.
+    specular: bool,
+    /// Area density with which the vertex was generated by its own subpath.
+    pdf_fwd: f32,
+    /// Area density with which the other subpath would generate the vertex.
+    pdf_rev: f32,
+}
+
+impl<'a> Vertex<'a> {
+    fn endpoint(point: Vec3A, normal: Vec3A, ray: Ray) -> Self {
+        Self {
+            point,
+            normal,
+            hit: None,
+            ray,
+            beta: Vec3A::ONE,
+            specular: false,
+            pdf_fwd: 0.,
+            pdf_rev: 0.,
+        }
+    }
+}
+
+/// Bidirectional path tracing: a camera subpath and a light subpath are connected at every
+/// pair of vertices and the resulting strategies are combined with multiple importance
+/// sampling. Strategies that would splat onto the film from the light side are not used.
+pub(crate) fn ray_color(
+    ray: &Ray,
+    scene: &Scene,
+    settings: &RenderSettings,
+    sampler: &mut dyn Sampler,
+    aov: &mut AovSample,
+) -> Vec3A {
+    let mut radiance = Vec3A::ZERO;
+
+    let mut camera = vec![Vertex::endpoint(ray.origin, Vec3A::ZERO, *ray)];
+    if let Some(beta) = random_walk(scene, settings, sampler, *ray, Vec3A::ONE, 1., &mut camera) {
+        let indirect = camera.len() > 2;
+        radiance += clamp_indirect(beta * scene.background, indirect, settings);
+    }
+    set_reverse_pdfs(&mut camera);
+    let Some(first) = camera.get(1) else {
+        return radiance;
+    };
+    if let Some(hit) = &first.hit {
+        *aov = AovSample::new(&first.ray, hit);
+    }
+
+    let light = light_subpath(scene, settings, sampler, first.point, ray.time);
+
+    for t in 2..=camera.len() {
+        for s in 0..=light.len() {
+            let edges = t - 1 + s;
+            if edges > settings.max_depth {
+                break;
+            }
+            let contribution = connect(scene, settings, &camera, &light, s, t);
+            radiance += clamp_indirect(contribution, edges >= 3, settings);
+        }
+    }
+
+    radiance
+}
+
+/// Extends `path` by following `ray` and sampling the BSDF at every hit. `pdf` is the solid
+/// angle density `ray` was sampled with. Returns the throughput of a ray that left the scene.
+fn random_walk<'a>(
+    scene: &'a Scene,
+    settings: &RenderSettings,
+    sampler: &mut dyn Sampler,
+    mut ray: Ray,
+    mut beta: Vec3A,
+    mut pdf: f32,
+    path: &mut Vec<Vertex<'a>>,
+) -> Option<Vec3A> {
+    for depth in 0..settings.max_depth {
+        let Some(hit) = surface_hit(scene.world.as_ref(), &ray, 0.001, f32::INFINITY) else {
+            return Some(beta);
+        };
+
+        let mut vertex = Vertex::endpoint(hit.point, hit.normal, ray);
+        vertex.beta = beta;
+        vertex.pdf_fwd = to_area(pdf, ray.origin, &vertex);
+        let srec = hit.material.sample(&ray, &hit, sampler);
+        vertex.hit = Some(hit);
+        let Some(srec) = srec else {
+            path.push(vertex);
+            break;
+        };
+        vertex.specular = srec.specular;
+        pdf = if srec.specular { 0. } else { srec.pdf };
+        ray = Ray::new(vertex.point, srec.direction, ray.time);
+        path.push(vertex);
+
+        beta *= srec.attenuation;
+        if depth >= settings.rr_min_depth {
+            let survival = beta.max_element().min(1.);
+            if sampler.get_1d() >= survival {
+                break;
+            }
+            beta /= survival;
+        }
+    }
+
+    None
+}
+
+/// Fills in the density with which each vertex would be sampled from its successor.
+fn set_reverse_pdfs(path: &mut [Vertex<'_>]) {
+    for i in 1..path.len().saturating_sub(1) {
+        path[i - 1].pdf_rev = if path[i].specular {
+            0.
+        } else {
+            scatter_pdf(&path[i], path[i + 1].point, &path[i - 1])
+        };
+    }
+}
+
+/// Starts a light subpath on the light seen from `origin` and follows it into the scene. The
+/// point on the light is chosen with the lights' `random`/`pdf_value` from the first camera
+/// vertex, which every strategy shares.
+fn light_subpath<'a>(
+    scene: &'a Scene,
+    settings: &RenderSettings,
+    sampler: &mut dyn Sampler,
+    origin: Vec3A,
+    time: f32,
+) -> Vec<Vertex<'a>> {
+    let direction = scene.lights.random(origin, sampler);
+    let to_light = Ray::new(origin, direction, time);
+    let Some(light_hit) = scene.lights.hit(&to_light, 0.001, f32::INFINITY) else {
+        return Vec::new();
+    };
+    let mut start = Vertex::endpoint(light_hit.point, light_hit.normal, to_light);
+    start.pdf_fwd = light_pdf(scene, origin, &start);
+    if !(start.pdf_fwd > 0. && start.pdf_fwd.is_finite()) {
+        return Vec::new();
+    }
+
+    let emission = sample_emission(
+        scene.world.as_ref(),
+        start.point,
+        start.normal,
+        time,
+        sampler,
+    );
+    let mut path = vec![start];
+    if let Some(emission) = emission {
+        // Le cos / (pdf_area * pdf_direction).
+        let beta = emission.radiance * path[0].normal.dot(emission.ray.direction).abs()
+            / (path[0].pdf_fwd * emission.pdf);
+        random_walk(
+            scene,
+            settings,
+            sampler,
+            emission.ray,
+            beta,
+            emission.pdf,
+            &mut path,
+        );
+        set_reverse_pdfs(&mut path);
+    }
+    path
+}
+
+/// Radiance carried by the path made of the first `t` camera vertices and the first `s` light
+/// vertices, already weighted by MIS.
+fn connect(
+    scene: &Scene,
+    settings: &RenderSettings,
+    camera: &[Vertex<'_>],
+    light: &[Vertex<'_>],
+    s: usize,
+    t: usize,
+) -> Vec3A {
+    let pt = &camera[t - 1];
+    let Some(pt_hit) = &pt.hit else {
+        return Vec3A::ZERO;
+    };
+
+    let contribution = if s == 0 {
+        pt.beta * pt_hit.material.emitted(&pt.ray, pt_hit)
+    } else {
+        let qs = &light[s - 1];
+        if pt.specular || qs.specular {
+            return Vec3A::ZERO;
+        }
+        let w = qs.point - pt.point;
+        let distance_squared = w.length_squared();
+        let distance = distance_squared.sqrt();
+        let direction = w / distance;
+
+        let f_pt = pt_hit.material.eval(&pt.ray, pt_hit, direction);
+        if f_pt == Vec3A::ZERO {
+            return Vec3A::ZERO;
+        }
+        let shadow_ray = Ray::new(pt.point, direction, pt.ray.time);
+
+        match &qs.hit {
+            // The start of the light subpath: the shadow ray finds the emitter and its radiance.
+            None => {
+                let Some(light_hit) =
+                    surface_hit(scene.world.as_ref(), &shadow_ray, 0.001, f32::INFINITY)
+                else {
+                    return Vec3A::ZERO;
+                };
+                if (light_hit.t - distance).abs() > 1e-3 * distance {
+                    return Vec3A::ZERO;
+                }
+                let emitted = light_hit.material.emitted(&shadow_ray, &light_hit);
+                let cosine = qs.normal.dot(direction).abs();
+                pt.beta * f_pt * emitted * cosine / (distance_squared * qs.pdf_fwd)
+            }
+            Some(qs_hit) => {
+                let f_qs = qs_hit.material.eval(&qs.ray, qs_hit, -direction);
+                if f_qs == Vec3A::ZERO {
+                    return Vec3A::ZERO;
+                }
+                if surface_hit(scene.world.as_ref(), &shadow_ray, 0.001, distance - 0.001).is_some()
+                {
+                    return Vec3A::ZERO;
+                }
+                pt.beta * f_pt * f_qs * qs.beta / distance_squared
+            }
+        }
+    };
+
+    if contribution == Vec3A::ZERO {
+        return Vec3A::ZERO;
+    }
+    contribution * mis_weight(scene, settings.mis_heuristic, camera, light, s, t)
+}
+
+/// Weight of strategy (`s`, `t`) among all strategies that could have produced the same path,
+/// computed from the ratios of their path densities.
+fn mis_weight(
+    scene: &Scene,
+    heuristic: MisHeuristic,
+    camera: &[Vertex<'_>],
+    light: &[Vertex<'_>],
+    s: usize,
+    t: usize,
+) -> f32 {
+    if s + t == 2 {
+        return 1.;
+    }
+
+    // Densities of the connection vertices and their predecessors as sampled from the other
+    // side, which depend on the strategy.
+    let pt = &camera[t - 1];
+    let pt_rev = match s {
+        0 => light_pdf(scene, camera[1].point, pt),
+        1 => emission_pdf(&light[0], pt),
+        _ => scatter_pdf(&light[s - 1], light[s - 2].point, pt),
+    };
+    let pt_prev_rev = match s {
+        _ if t < 3 => 0.,
+        0 => emission_pdf(pt, &camera[t - 2]),
+        _ => scatter_pdf(pt, light[s - 1].point, &camera[t - 2]),
+    };
+    let qs_rev = match s {
+        0 => 0.,
+        _ => scatter_pdf(pt, camera[t - 2].point, &light[s - 1]),
+    };
+    let qs_prev_rev = match s {
+        0 | 1 => 0.,
+        _ => scatter_pdf(&light[s - 1], pt.point, &light[s - 2]),
+    };
+
+    let power = |ratio: f32| match heuristic {
+        MisHeuristic::Balance => ratio,
+        MisHeuristic::Power => ratio * ratio,
+    };
+    // Densities of vertices sampled through a delta lobe are stored as 0. They appear on both
+    // sides of the ratios and cancel, so they count as 1. Any other density of 0 means the
+    // strategy cannot produce the path and ends the chain of ratios.
+    let remap = |pdf: f32, delta: bool| if delta { 1. } else { pdf };
+
+    let mut sum = 0.;
+    let mut ratio = 1.;
+    // Strategies with fewer camera vertices; at least one surface vertex stays on the camera
+    // side since nothing is splatted from the light subpath.
+    for i in (2..t).rev() {
+        let rev = if i == t - 1 {
+            pt_rev
+        } else if i == t - 2 {
+            pt_prev_rev
+        } else {
+            camera[i].pdf_rev
+        };
+        let rev_delta = i + 1 < t && camera[i + 1].specular;
+        ratio *= remap(rev, rev_delta) / remap(camera[i].pdf_fwd, camera[i - 1].specular);
+        if !camera[i].specular && !camera[i - 1].specular {
+            sum += power(ratio);
+        }
+    }
+
+    ratio = 1.;
+    // Strategies with fewer light vertices.
+    for i in (0..s).rev() {
+        let rev = if i == s - 1 {
+            qs_rev
+        } else if i + 2 == s {
+            qs_prev_rev
+        } else {
+            light[i].pdf_rev
+        };
+        let rev_delta = i + 1 < s && light[i + 1].specular;
+        let previous_specular = i > 0 && light[i - 1].specular;
+        ratio *= remap(rev, rev_delta) / remap(light[i].pdf_fwd, previous_specular);
+        if !light[i].specular && !previous_specular {
+            sum += power(ratio);
+        }
+    }
+
+    let weight = 1. / (1. + sum);
+    if weight.is_finite() {
+        weight
+    } else {
+        0.
+    }
+}
+
+/// Converts a solid angle density at `from` into an area density at `to`.
+fn to_area(pdf: f32, from: Vec3A, to: &Vertex<'_>) -> f32 {
+    let w = to.point - from;
+    let distance_squared = w.length_squared();
+    if distance_squared == 0. {
+        return 0.;
+    }
+    pdf * to.normal.dot(w).abs() / (distance_squared * distance_squared.sqrt())
+}
+
+/// Area density at `to` of scattering at `vertex` when arriving from `from`.
+fn scatter_pdf(vertex: &Vertex<'_>, from: Vec3A, to: &Vertex<'_>) -> f32 {
+    let Some(hit) = &vertex.hit else {
+        return 0.;
+    };
+    let incoming = Ray::new(from, vertex.point - from, vertex.ray.time);
+    let direction = (to.point - vertex.point).normalize();
+    to_area(
+        hit.material.pdf(&incoming, hit, direction),
+        vertex.point,
+        to,
+    )
+}
+
+/// Area density at `to` of the direction a light subpath leaves `emitter` in.
+fn emission_pdf(emitter: &Vertex<'_>, to: &Vertex<'_>) -> f32 {
+    let direction = (to.point - emitter.point).normalize();
+    to_area(
+        0.5 * emitter.normal.dot(direction).abs() / PI,
+        emitter.point,
+        to,
+    )
+}
+
+/// Area density with which a light subpath starts at `vertex` when the light is sampled from
+/// `origin`. Points hidden behind another light are never chosen.
+fn light_pdf(scene: &Scene, origin: Vec3A, vertex: &Vertex<'_>) -> f32 {
+    let w = vertex.point - origin;
+    match scene
+        .lights
+        .hit(&Ray::new(origin, w, vertex.ray.time), 0.001, f32::INFINITY)
+    {
+        Some(hit) if (hit.t - 1.).abs() < 1e-3 => {
+            to_area(scene.lights.pdf_value(origin, w), origin, vertex)
+        }
+        _ => 0.,
+    }
+}
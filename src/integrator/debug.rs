@@ -0,0 +1,67 @@
+use crate::{aov::AovSample, hittable::bvh::TraversalStats, ray::Ray, scene::Scene};
+use glam::Vec3A;
+
+/// Quantity shown by [`Integrator::Debug`](super::Integrator::Debug). Rays that miss the scene
+/// are black, except in the BVH heatmaps which count the work spent on every ray.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DebugMode {
+    /// Normal on the side facing the ray, mapped from [-1, 1] to [0, 1].
+    Normal,
+    /// `u` in red and `v` in green.
+    Uv,
+    /// Distance to the hit on a heat ramp that turns red at `max_distance`.
+    Depth { max_distance: f32 },
+    /// Green where the ray hits the front of a surface and red where it hits the back, which
+    /// shows normals that point the wrong way.
+    FrontFace,
+    /// AABB tests made by the BVH, hit or missed, on a heat ramp that turns red at `max`.
+    AabbTests { max: u32 },
+    /// BVH nodes whose box the ray entered on a heat ramp that turns red at `max`.
+    NodeVisits { max: u32 },
+}
+
+pub(crate) fn ray_color(ray: &Ray, scene: &Scene, mode: DebugMode, aov: &mut AovSample) -> Vec3A {
+    let mut stats = TraversalStats::default();
+    let hit = scene
+        .world
+        .hit_with_stats(ray, 0.001, f32::INFINITY, &mut stats);
+    let TraversalStats {
+        node_visits,
+        aabb_tests,
+    } = stats;
+
+    if let Some(hit) = &hit {
+        *aov = AovSample::new(ray, hit);
+    }
+    match (mode, hit) {
+        (DebugMode::AabbTests { max }, _) => heat(aabb_tests as f32 / max.max(1) as f32),
+        (DebugMode::NodeVisits { max }, _) => heat(node_visits as f32 / max.max(1) as f32),
+        (_, None) => Vec3A::ZERO,
+        (DebugMode::Normal, Some(hit)) => 0.5 * (hit.normal.normalize() + Vec3A::ONE),
+        (DebugMode::Uv, Some(hit)) => Vec3A::new(hit.u, hit.v, 0.),
+        (DebugMode::Depth { max_distance }, Some(hit)) => {
+            heat(hit.t * ray.direction.length() / max_distance)
+        }
+        (DebugMode::FrontFace, Some(hit)) => {
+            if hit.front_face {
+                Vec3A::Y
+            } else {
+                Vec3A::X
+            }
+        }
+    }
+}
+
+/// Blue, cyan, green, yellow, red as `t` goes from 0 to 1.
+fn heat(t: f32) -> Vec3A {
+    const STOPS: [Vec3A; 5] = [
+        Vec3A::Z,
+        Vec3A::new(0., 1., 1.),
+        Vec3A::Y,
+        Vec3A::new(1., 1., 0.),
+        Vec3A::X,
+    ];
+    let x = t.clamp(0., 1.) * (STOPS.len() - 1) as f32;
+    let i = (x as usize).min(STOPS.len() - 2);
+    STOPS[i].lerp(STOPS[i + 1], x - i as f32)
+}
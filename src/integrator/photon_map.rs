@@ -0,0 +1,178 @@
+use super::{sample_emission, surface_hit};
+use crate::{
+    hittable::HitRecord, progress::CancellationToken, ray::Ray, scene::Scene, RenderSettings,
+};
+use glam::Vec3A;
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use std::{collections::HashMap, f32::consts::PI};
+
+/// Salt separating the photon streams from the camera sample streams.
+const PHOTON_SEED: u64 = 0x7068_6f74_6f6e_7321;
+
+/// Settings of [`Integrator::PhotonMapping`](super::Integrator::PhotonMapping).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PhotonSettings {
+    /// Photons emitted for every photon map.
+    pub photons_per_pass: usize,
+    /// Pixel samples that share one photon map.
+    pub samples_per_pass: usize,
+    /// Gather radius of the first pass, in scene units.
+    pub initial_radius: f32,
+    /// Share of the photons kept from one pass to the next, in (0, 1). Smaller values shrink
+    /// the radius faster.
+    pub alpha: f32,
+}
+
+impl PhotonSettings {
+    /// Gather radius of pass `pass`, reduced after every pass as in Knaus and Zwicker's
+    /// "Progressive Photon Mapping: A Probabilistic Approach" so the average over all passes
+    /// converges.
+    pub fn radius(&self, pass: usize) -> f32 {
+        let mut radius_squared = self.initial_radius * self.initial_radius;
+        for i in 1..=pass {
+            radius_squared *= (i as f32 + self.alpha) / (i as f32 + 1.);
+        }
+        radius_squared.sqrt()
+    }
+}
+
+impl Default for PhotonSettings {
+    fn default() -> Self {
+        Self {
+            photons_per_pass: 100_000,
+            samples_per_pass: 4,
+            initial_radius: 1.,
+            alpha: 2. / 3.,
+        }
+    }
+}
+
+struct Photon {
+    position: Vec3A,
+    /// Normalized direction of travel.
+    direction: Vec3A,
+    power: Vec3A,
+}
+
+/// Caustic photons of one pass: light that reached a diffuse surface through one or more
+/// specular bounces, stored in a hash grid of cells twice the gather radius wide.
+pub struct PhotonMap {
+    radius: f32,
+    cells: HashMap<[i32; 3], Vec<Photon>>,
+}
+
+impl PhotonMap {
+    /// Traces the photons of `pass`. `None` if `cancel` is triggered in the meantime.
+    pub fn build(
+        scene: &Scene,
+        settings: &RenderSettings,
+        pass: usize,
+        cancel: &CancellationToken,
+    ) -> Option<Self> {
+        let radius = settings.photons.radius(pass);
+        let photons = (0..settings.photons.photons_per_pass)
+            .into_par_iter()
+            .filter_map(|index| {
+                if cancel.is_cancelled() {
+                    return None;
+                }
+                trace_photon(scene, settings, pass, index)
+            })
+            .collect::<Vec<_>>();
+        if cancel.is_cancelled() {
+            return None;
+        }
+
+        let mut map = Self {
+            radius,
+            cells: HashMap::new(),
+        };
+        for photon in photons {
+            map.cells
+                .entry(map.cell(photon.position))
+                .or_default()
+                .push(photon);
+        }
+        Some(map)
+    }
+
+    fn cell(&self, point: Vec3A) -> [i32; 3] {
+        (point / (2. * self.radius)).floor().as_ivec3().to_array()
+    }
+
+    /// Caustic radiance leaving `hit` back along `ray`, from the photons within the gather
+    /// radius.
+    pub fn estimate(&self, ray: &Ray, hit: &HitRecord) -> Vec3A {
+        let radius_squared = self.radius * self.radius;
+        let min = self.cell(hit.point - Vec3A::splat(self.radius));
+        let max = self.cell(hit.point + Vec3A::splat(self.radius));
+
+        let mut sum = Vec3A::ZERO;
+        for x in min[0]..=max[0] {
+            for y in min[1]..=max[1] {
+                for z in min[2]..=max[2] {
+                    let Some(photons) = self.cells.get(&[x, y, z]) else {
+                        continue;
+                    };
+                    for photon in photons {
+                        if (photon.position - hit.point).length_squared() > radius_squared {
+                            continue;
+                        }
+                        let cosine = -photon.direction.dot(hit.normal);
+                        if cosine <= 0. {
+                            continue;
+                        }
+                        let bsdf = hit.material.eval(ray, hit, -photon.direction) / cosine;
+                        sum += bsdf * photon.power;
+                    }
+                }
+            }
+        }
+        sum / (PI * radius_squared)
+    }
+}
+
+/// Emits photon `index` of `pass` and follows it through specular bounces. Returns it where it
+/// lands on the first diffuse surface, unless it got there without any specular bounce.
+fn trace_photon(
+    scene: &Scene,
+    settings: &RenderSettings,
+    pass: usize,
+    index: usize,
+) -> Option<Photon> {
+    let photons = &settings.photons;
+    let mut sampler = settings.sampler.sampler(
+        settings.seed ^ PHOTON_SEED,
+        pass as u64,
+        index as u64,
+        photons.photons_per_pass,
+    );
+    let surface = scene.lights.sample_surface(sampler.as_mut())?;
+    let emission = sample_emission(
+        scene.world.as_ref(),
+        surface.point,
+        surface.normal,
+        0.,
+        sampler.as_mut(),
+    )?;
+    let (mut ray, mut hit) = (emission.ray, emission.receiver);
+    // Le cos / (pdf_area * pdf_direction * photon count).
+    let mut power = emission.radiance * surface.normal.dot(ray.direction).abs()
+        / (surface.pdf * emission.pdf * photons.photons_per_pass as f32);
+
+    for bounce in 0..settings.max_depth {
+        let srec = hit.material.sample(&ray, &hit, sampler.as_mut())?;
+        if !srec.specular {
+            return (bounce > 0).then(|| Photon {
+                position: hit.point,
+                direction: ray.direction.normalize(),
+                power,
+            });
+        }
+        power *= srec.attenuation;
+        ray = Ray::new(hit.point, srec.direction, ray.time);
+        hit = surface_hit(scene.world.as_ref(), &ray, 0.001, f32::INFINITY)?;
+    }
+
+    None
+}
@@ -1,167 +1,596 @@
 #![feature(core_intrinsics)]
-use crate::camera::Camera;
-use anyhow::Result;
+use anyhow::{ensure, Result};
+use aov::{AovSample, Aovs};
+use checkpoint::{Checkpoint, PixelState};
+use crop::CropWindow;
+use denoise::Denoiser;
+use film::{filter_margin, TileFilm};
+use filter::{BoxFilter, Filter};
+use framebuffer::Framebuffer;
 use glam::Vec3A;
-use hittable::{
-    bvh::BvhTree,
-    rect::{AARect, Plane},
-    Hittable,
+use integrator::{
+    photon_map::{PhotonMap, PhotonSettings},
+    Integrator, MisHeuristic,
 };
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use material::diffuse_light::DiffuseLight;
-use pdf::{CosinePdf, HittablePdf, MixturePdf, Pdf};
-use rand::{rngs::SmallRng, Rng, SeedableRng};
-use ray::Ray;
-use rayon::prelude::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
-use scene::cornell_box;
+use progress::{CancellationToken, Cancelled, ProgressSink, SilentProgress};
+use rand::rngs::SmallRng;
+use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use sampler::{SamplerKind, StratifiedSampler};
+use scene::Scene;
 use std::{
-    f32::INFINITY,
-    io::{BufWriter, Write},
-    sync::Arc,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
+use tile::{tiles, Tile};
 
-mod camera;
-mod hittable;
-mod material;
+pub mod aov;
+pub mod camera;
+pub mod checkpoint;
+pub mod crop;
+pub mod denoise;
+pub mod film;
+pub mod filter;
+pub mod framebuffer;
+pub mod hittable;
+pub mod integrator;
+pub mod material;
+pub mod medium;
 pub mod onb;
+pub mod output;
 pub mod pdf;
-mod ray;
-mod scene;
-mod texture;
+pub mod progress;
+pub mod ray;
+pub mod rng;
+pub mod sampler;
+pub mod scene;
+pub mod spectrum;
+pub mod texture;
+pub mod tile;
+pub mod tonemap;
 mod vec;
 
 pub type Rand = SmallRng;
 
-fn ray_color<TWorld: Hittable, TLight: Hittable + 'static>(
-    ray: &Ray,
-    background: &Vec3A,
-    world: &TWorld,
-    lights: Arc<TLight>,
-    depth: usize,
-    rng: &mut Rand,
-) -> Vec3A {
-    if depth <= 0 {
-        return Vec3A::ZERO;
+pub struct RenderSettings {
+    pub img_width: usize,
+    pub img_height: usize,
+    /// Samples per pixel, or the upper bound on them when adaptive sampling is enabled.
+    pub samples_per_pixel: usize,
+    /// Samples every pixel takes before adaptive sampling may stop it.
+    pub min_samples_per_pixel: usize,
+    /// Relative standard error of a pixel's luminance at which it stops sampling.
+    /// `None` disables adaptive sampling.
+    pub noise_threshold: Option<f32>,
+    /// Hard cap on the path length; Russian roulette normally ends paths well before it.
+    pub max_depth: usize,
+    /// Number of bounces before Russian roulette starts terminating paths.
+    pub rr_min_depth: usize,
+    /// Light transport algorithm that estimates the radiance of every camera ray.
+    pub integrator: Integrator,
+    /// Photon passes of [`Integrator::PhotonMapping`]; ignored by the other integrators.
+    pub photons: PhotonSettings,
+    /// How light and BSDF samples are weighted against each other for direct lighting.
+    pub mis_heuristic: MisHeuristic,
+    /// Seed every per-sample random stream is derived from.
+    pub seed: u64,
+    /// Trace four wavelengths per sample instead of RGB, so dispersive glass splits light. Only
+    /// the camera paths of the path tracer and photon mapping are spectral; bidirectional path
+    /// tracing refuses to render with it.
+    pub spectral: bool,
+    /// Sequence the pixel, lens, light and BSDF samples are drawn from.
+    pub sampler: SamplerKind,
+    /// Edge length in pixels of the square tiles handed to the worker threads.
+    pub tile_size: usize,
+    /// File the accumulated samples are periodically saved to, see [`resume_with`].
+    pub checkpoint_path: Option<PathBuf>,
+    /// Minimum time between two checkpoint saves.
+    pub checkpoint_interval: Duration,
+    /// Upper bound on any channel of a single indirect lighting contribution. `None` keeps
+    /// the estimate unbiased.
+    pub max_radiance: Option<f32>,
+    /// Reconstruction filter the samples are splatted with.
+    pub filter: Arc<dyn Filter>,
+    /// Traces only part of the frame. `None` renders all of it.
+    pub crop: Option<CropWindow>,
+    /// Post-pass run on the finished image. `None` returns the noisy estimate.
+    pub denoiser: Option<Denoiser>,
+}
+
+impl RenderSettings {
+    pub fn aspect_ratio(&self) -> f32 {
+        self.img_width as f32 / self.img_height as f32
     }
 
-    if let Some(hit) = world.hit(ray, 0.001, INFINITY) {
-        let emitted = hit.material.emitted(ray, &hit);
-        if let Some((_, albedo, _)) = hit.material.scatter(ray, &hit, rng) {
-            let p0: Arc<_> = HittablePdf::new(hit.point, lights.clone()).into();
-            let p1: Arc<_> = CosinePdf::new(&hit.normal).into();
-            let mixed_pdf = MixturePdf::new((p0, p1));
-
-            let scattered = Ray::new(hit.point, mixed_pdf.generate(rng), ray.time);
-            let color = ray_color(&scattered, background, world, lights, depth - 1, rng);
-            let pdf = mixed_pdf.value(scattered.direction);
-            let scatterd_pdf = hit.material.scattering_pdf(ray, &hit, &scattered);
-
-            emitted
-                + Vec3A::new(
-                    albedo.x * scatterd_pdf * color.x / pdf,
-                    albedo.y * scatterd_pdf * color.y / pdf,
-                    albedo.z * scatterd_pdf * color.z / pdf,
-                )
-        } else {
-            emitted
+    /// Pixels the render returns: the crop window, or the whole frame.
+    pub fn window(&self) -> Tile {
+        match &self.crop {
+            Some(crop) => crop.pixels(self.img_width, self.img_height),
+            None => Tile {
+                x: 0,
+                y: 0,
+                width: self.img_width,
+                height: self.img_height,
+            },
         }
-    } else {
-        *background
     }
 }
 
-pub fn draw<W: Write>(
-    img_height: usize,
-    img_width: usize,
-    samples_per_pixel: usize,
-    max_depth: usize,
-    writer: &mut BufWriter<W>,
-) -> Result<()> {
-    // Image
-    let aspect_ratio = img_width as f32 / img_height as f32;
-
-    // Progress
-    let multi_pb = MultiProgress::new();
-    let sub_pb_style =
-        ProgressStyle::with_template("           ┣ {wide_bar:.cyan/blue} {pos:>7}/{len:7} {msg}")?
-            .progress_chars("##-");
-    let main_pb = multi_pb.add(ProgressBar::new(img_height as u64));
-    main_pb.set_style(
-        ProgressStyle::with_template(
-            "[{elapsed_precise}] ┏ {wide_bar:.cyan/blue} {pos:>7}/{len:7} {msg}",
-        )?
-        .progress_chars("##-"),
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            img_width: 600,
+            img_height: 600,
+            samples_per_pixel: 100,
+            min_samples_per_pixel: 16,
+            noise_threshold: None,
+            max_depth: 50,
+            rr_min_depth: 3,
+            integrator: Integrator::default(),
+            photons: PhotonSettings::default(),
+            mis_heuristic: MisHeuristic::default(),
+            seed: 0,
+            spectral: false,
+            sampler: SamplerKind::default(),
+            tile_size: 32,
+            checkpoint_path: None,
+            checkpoint_interval: Duration::from_secs(60),
+            max_radiance: None,
+            filter: Arc::new(BoxFilter::default()),
+            crop: None,
+            denoiser: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Samples traced, including the rejected ones.
+    pub samples: u64,
+    /// Samples that came back NaN or infinite and were discarded.
+    pub rejected_samples: u64,
+}
+
+pub struct RenderOutput {
+    /// Final image, denoised when [`RenderSettings::denoiser`] is set.
+    pub image: Framebuffer,
+    /// The image before denoising, if the denoiser was asked to keep it.
+    pub raw: Option<Framebuffer>,
+    pub aovs: Aovs,
+    pub stats: RenderStats,
+}
+
+fn luminance(color: Vec3A) -> f32 {
+    color.dot(Vec3A::new(0.2126, 0.7152, 0.0722))
+}
+
+/// Keeps sampling pixel `(x, y)`, row 0 being the top of the image, until it reaches `limit`
+/// samples, adaptive sampling considers it converged or `cancel` is triggered. Picks up from
+/// whatever `state` holds.
+#[allow(clippy::too_many_arguments)]
+fn render_pixel(
+    scene: &Scene,
+    settings: &RenderSettings,
+    photons: Option<&PhotonMap>,
+    x: usize,
+    y: usize,
+    limit: usize,
+    state: &mut PixelState,
+    film: &mut TileFilm,
+    cancel: &CancellationToken,
+) {
+    let pixel = (y * settings.img_width + x) as u64;
+    let mut sampler = settings.sampler.sampler(
+        settings.seed,
+        pixel,
+        state.traced() as u64,
+        settings.samples_per_pixel,
+    );
+
+    while (state.traced() as usize) < limit && !cancel.is_cancelled() {
+        if let Some(threshold) = settings.noise_threshold {
+            let samples = state.samples as usize;
+            if samples >= settings.min_samples_per_pixel.max(2) {
+                let variance = state.m2 / (samples - 1) as f32;
+                let std_error = (variance / samples as f32).sqrt();
+                if std_error <= threshold * state.mean.max(1e-4) {
+                    break;
+                }
+            }
+        }
+
+        // Rejected samples still use up their index, so the sequence moves on past them.
+        sampler.start_sample(state.traced() as u64);
+        // Film position in pixels from the top-left corner; the camera's v runs bottom-up.
+        let jitter = sampler.get_2d();
+        let fx = x as f32 + jitter.x;
+        let fy = y as f32 + jitter.y;
+        let u = fx / (settings.img_width - 1) as f32;
+        let v = (settings.img_height as f32 - fy) / (settings.img_height - 1) as f32;
+
+        let ray = scene.camera.get_ray(u, v, sampler.as_mut());
+        let mut aov = AovSample::default();
+        let color = settings.integrator.radiance(
+            &ray,
+            scene,
+            settings,
+            photons,
+            sampler.as_mut(),
+            &mut aov,
+        );
+        // One NaN or infinity would poison the whole pixel, so drop the sample.
+        if !color.is_finite() {
+            state.rejected += 1;
+            continue;
+        }
+        state.sum += color;
+        state.aov.accumulate(&aov, state.samples == 0);
+        state.samples += 1;
+        film.splat(settings.filter.as_ref(), fx, fy, color);
+
+        // Welford's running mean and squared deviation of the luminance.
+        let lum = luminance(color);
+        let delta = lum - state.mean;
+        state.mean += delta / state.samples as f32;
+        state.m2 += delta * (lum - state.mean);
+    }
+}
+
+/// Renders `scene` without reporting progress.
+pub fn render(scene: &Scene, settings: &RenderSettings) -> Result<RenderOutput> {
+    render_with(
+        scene,
+        settings,
+        &SilentProgress,
+        &CancellationToken::default(),
+    )
+}
+
+/// Renders `scene` tile by tile, reporting finished tiles to `progress`. Returns a [`Cancelled`]
+/// error as soon as `cancel` is triggered.
+pub fn render_with(
+    scene: &Scene,
+    settings: &RenderSettings,
+    progress: &dyn ProgressSink,
+    cancel: &CancellationToken,
+) -> Result<RenderOutput> {
+    let checkpoint = Checkpoint::new(scene, settings);
+    resume_with(scene, settings, progress, cancel, checkpoint)
+}
+
+/// Continues the render saved in `checkpoint` up to the sample budget of `settings`, which may be
+/// larger than the one the checkpoint was started with, except with the stratified sampler whose
+/// strata depend on it. With the same settings the result is identical to an uninterrupted
+/// render.
+pub fn resume_with(
+    scene: &Scene,
+    settings: &RenderSettings,
+    progress: &dyn ProgressSink,
+    cancel: &CancellationToken,
+    checkpoint: Checkpoint,
+) -> Result<RenderOutput> {
+    ensure!(
+        !(settings.spectral && settings.integrator == Integrator::Bidirectional),
+        "Bidirectional path tracing does not support spectral rendering"
+    );
+    ensure!(
+        checkpoint.width == settings.img_width && checkpoint.height == settings.img_height,
+        "Checkpoint is {}x{} but the render is {}x{}",
+        checkpoint.width,
+        checkpoint.height,
+        settings.img_width,
+        settings.img_height
+    );
+    ensure!(
+        checkpoint.seed == settings.seed,
+        "Checkpoint was rendered with seed {} but the render uses {}",
+        checkpoint.seed,
+        settings.seed
+    );
+    ensure!(
+        checkpoint.sampler == settings.sampler,
+        "Checkpoint was rendered with the {:?} sampler but the render uses {:?}",
+        checkpoint.sampler,
+        settings.sampler
+    );
+    // The strata only cover the samples they were laid out for, so their count cannot change.
+    ensure!(
+        settings.sampler != SamplerKind::Stratified
+            || StratifiedSampler::strata(checkpoint.samples_per_pixel)
+                == StratifiedSampler::strata(settings.samples_per_pixel),
+        "Stratified checkpoint was rendered with {} samples per pixel and cannot be resumed with {}",
+        checkpoint.samples_per_pixel,
+        settings.samples_per_pixel
+    );
+    ensure!(
+        checkpoint.tile_size == settings.tile_size
+            && checkpoint.filter_radius == settings.filter.radius(),
+        "Checkpoint was rendered with other tiles or another filter footprint"
+    );
+    ensure!(
+        checkpoint.integrator == settings.integrator && checkpoint.spectral == settings.spectral,
+        "Checkpoint was rendered with the {:?} integrator{} but the render uses {:?}{}",
+        checkpoint.integrator,
+        if checkpoint.spectral {
+            " in spectral mode"
+        } else {
+            ""
+        },
+        settings.integrator,
+        if settings.spectral {
+            " in spectral mode"
+        } else {
+            ""
+        }
+    );
+    ensure!(
+        checkpoint.max_depth == settings.max_depth
+            && checkpoint.rr_min_depth == settings.rr_min_depth,
+        "Checkpoint was rendered with depth {} and Russian roulette from {} but the render uses {} and {}",
+        checkpoint.max_depth,
+        checkpoint.rr_min_depth,
+        settings.max_depth,
+        settings.rr_min_depth
+    );
+    ensure!(
+        checkpoint.window == settings.window(),
+        "Checkpoint covers {:?} but the render {:?}",
+        checkpoint.window,
+        settings.window()
+    );
+    ensure!(
+        checkpoint.noise_threshold == settings.noise_threshold
+            && (settings.noise_threshold.is_none()
+                || checkpoint.min_samples_per_pixel == settings.min_samples_per_pixel),
+        "Checkpoint was rendered with other adaptive sampling settings"
+    );
+    ensure!(
+        checkpoint.scene == scene.fingerprint(),
+        "Checkpoint was rendered from another scene"
     );
 
-    // World
-    let mut rng = SmallRng::from_entropy();
-    let mut world = cornell_box();
-
-    let light = Arc::new(DiffuseLight::from(Vec3A::new(15., 15., 15.)));
-    let lights: Arc<_> = AARect::new(Plane::XZ, (213., 343.), (227., 332.), 554., light).into();
-    let world = BvhTree::new(&mut world.objects, (0., 1.), &mut rng);
-    let background = Vec3A::ZERO;
-
-    // Camera
-    let look_from = Vec3A::new(278., 278., -800.);
-    let look_at = Vec3A::new(278., 278., 0.);
-    let focus_dist = (look_from - look_at).length();
-    let aperture = 0.;
-    let camera = Camera::new(
-        look_from,
-        look_at,
-        Vec3A::new(0.0, 1.0, 0.0),
-        40.0,
-        aspect_ratio,
-        aperture,
-        focus_dist,
-        (0., 1.),
+    let (width, height) = (settings.img_width, settings.img_height);
+    let window = settings.window();
+    ensure!(
+        window.width > 0 && window.height > 0,
+        "Crop window {:?} is empty",
+        settings.crop
     );
+    // Pixels within the filter radius of the window splat into it, so they are traced too and
+    // the window comes out exactly as in a full render.
+    let traced = window.expand(filter_margin(settings.filter.radius()), width, height);
+    let tiles = tiles(width, height, settings.tile_size)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, tile)| tile.overlaps(&traced))
+        .collect::<Vec<_>>();
+    let checkpoint = Mutex::new(checkpoint);
+    let last_save = Mutex::new(Instant::now());
+
+    let rendered = match settings.integrator {
+        Integrator::PhotonMapping => {
+            // Every pass traces a fresh photon map with a smaller radius and takes the next
+            // `samples_per_pass` samples of every pixel with it. A resumed render restarts from
+            // the pass of its least sampled pixel, so each sample still sees the same map.
+            let per_pass = settings.photons.samples_per_pass.max(1);
+            let passes = settings.samples_per_pixel.div_ceil(per_pass);
+            let first = {
+                let checkpoint = checkpoint.lock().unwrap();
+                let done = traced
+                    .pixels()
+                    .map(|(x, y)| checkpoint.pixels[y * width + x].traced())
+                    .min();
+                done.unwrap_or(0) as usize / per_pass
+            };
+
+            progress.start((tiles.len() * passes.saturating_sub(first)) as u64);
+            let mut rendered = Ok(());
+            for pass in first..passes {
+                if cancel.is_cancelled() {
+                    break;
+                }
+                let Some(photons) = PhotonMap::build(scene, settings, pass, cancel) else {
+                    break;
+                };
+                let limit = ((pass + 1) * per_pass).min(settings.samples_per_pixel);
+                rendered = render_tiles(
+                    scene,
+                    settings,
+                    Some(&photons),
+                    limit,
+                    &tiles,
+                    &traced,
+                    &checkpoint,
+                    &last_save,
+                    progress,
+                    cancel,
+                );
+                if rendered.is_err() {
+                    break;
+                }
+            }
+            rendered
+        }
+        _ => {
+            progress.start(tiles.len() as u64);
+            render_tiles(
+                scene,
+                settings,
+                None,
+                settings.samples_per_pixel,
+                &tiles,
+                &traced,
+                &checkpoint,
+                &last_save,
+                progress,
+                cancel,
+            )
+        }
+    };
+    progress.finish();
+    rendered?;
+
+    let checkpoint = checkpoint.into_inner().unwrap();
+    if let Some(path) = &settings.checkpoint_path {
+        checkpoint.save(path)?;
+    }
+    if cancel.is_cancelled() {
+        return Err(Cancelled.into());
+    }
 
-    // Render
-    multi_pb.println("✨ Generating...")?;
-    writeln!(writer, "P3\n{} {}\n255", img_width, img_height)?;
-    let image = (0..img_height)
-        .into_par_iter()
-        .rev()
-        .flat_map(|y| {
-            main_pb.inc(1);
-            let width_pb = multi_pb.add(ProgressBar::new(img_width as u64));
-            width_pb.set_style(sub_pb_style.clone());
-            let mut rng = SmallRng::from_entropy();
-            (0..img_width)
-                .flat_map(|x| {
-                    width_pb.inc(1);
-                    let scale = 1.0 / samples_per_pixel as f32;
-                    (0..samples_per_pixel)
-                        .map(|_| {
-                            let u = (x as f32 + rng.gen::<f32>()) / (img_width - 1) as f32;
-                            let v = (y as f32 + rng.gen::<f32>()) / (img_height - 1) as f32;
-
-                            let ray = camera.get_ray(u, v, &mut rng);
-                            ray_color(
-                                &ray,
-                                &background,
-                                &world,
-                                lights.clone(),
-                                max_depth,
-                                &mut rng,
-                            )
-                        })
-                        .sum::<Vec3A>()
-                        .to_array()
-                        .map(|c| (256.0 * (c * scale).sqrt().clamp(0.0, 0.999)) as u8)
-                        .to_vec()
-                })
-                .collect::<Vec<u8>>()
+    let mut image = checkpoint.framebuffer();
+    let mut aovs = checkpoint.aovs();
+    if settings.crop.is_some() {
+        image = image.crop(&window);
+        aovs = aovs.crop(&window);
+    }
+    let (mut image, mut raw) = match &settings.denoiser {
+        Some(denoiser) => {
+            let denoised = denoiser.apply(&image, &aovs);
+            (denoised, denoiser.keep_raw.then_some(image))
+        }
+        None => (image, None),
+    };
+    if let Some(CropWindow {
+        full_frame: true, ..
+    }) = settings.crop
+    {
+        image = image.uncrop(&window, width, height);
+        raw = raw.map(|raw| raw.uncrop(&window, width, height));
+        aovs = aovs.uncrop(&window, width, height);
+    }
+    Ok(RenderOutput {
+        image,
+        raw,
+        aovs,
+        stats: checkpoint.stats(),
+    })
+}
+
+/// Renders the pixels of `tiles`, each paired with its index in the full tile grid, that lie in
+/// `traced` up to `limit` samples per pixel, storing the results in `checkpoint`.
+#[allow(clippy::too_many_arguments)]
+fn render_tiles(
+    scene: &Scene,
+    settings: &RenderSettings,
+    photons: Option<&PhotonMap>,
+    limit: usize,
+    tiles: &[(usize, Tile)],
+    traced: &Tile,
+    checkpoint: &Mutex<Checkpoint>,
+    last_save: &Mutex<Instant>,
+    progress: &dyn ProgressSink,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    tiles
+        .par_iter()
+        .try_for_each(|&(index, ref tile)| -> Result<()> {
+            if cancel.is_cancelled() {
+                return Ok(());
+            }
+
+            let (mut states, mut film) = {
+                let checkpoint = checkpoint.lock().unwrap();
+                (checkpoint.read_tile(tile), checkpoint.films[index].clone())
+            };
+            for ((x, y), state) in tile.pixels().zip(states.iter_mut()) {
+                if traced.contains(x, y) {
+                    render_pixel(
+                        scene, settings, photons, x, y, limit, state, &mut film, cancel,
+                    );
+                }
+            }
+
+            let mut checkpoint = checkpoint.lock().unwrap();
+            checkpoint.write_tile(tile, &states);
+            checkpoint.films[index] = film;
+            // Only a snapshot is taken under the lock; the other workers keep going while it is
+            // written. A save already in progress makes the others skip theirs.
+            let due = settings.checkpoint_path.as_ref().and_then(|path| {
+                let last_save = last_save.try_lock().ok()?;
+                (last_save.elapsed() >= settings.checkpoint_interval)
+                    .then(|| (path, last_save, checkpoint.clone()))
+            });
+            drop(checkpoint);
+            if let Some((path, mut last_save, snapshot)) = due {
+                snapshot.save(path)?;
+                *last_save = Instant::now();
+            }
+
+            let pixels = states.iter().map(PixelState::radiance).collect::<Vec<_>>();
+            progress.tile_finished(tile, &pixels);
+            progress.advance(1);
+
+            Ok(())
         })
-        .collect::<Vec<u8>>();
-    for col in image.chunks(3) {
-        writeln!(writer, "{} {} {}", col[0], col[1], col[2])?;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use camera::Camera;
+    use hittable::{hittable_list::HittableList, sphere::Sphere};
+    use material::diffuse_light::DiffuseLight;
+    use rand::SeedableRng;
+    use rayon::ThreadPoolBuilder;
+    use scene::cornell_box;
+
+    fn render_on_threads(threads: usize) -> Framebuffer {
+        let settings = RenderSettings {
+            img_width: 24,
+            img_height: 16,
+            samples_per_pixel: 4,
+            tile_size: 8,
+            ..Default::default()
+        };
+        let scene = cornell_box(
+            settings.aspect_ratio(),
+            &mut Rand::seed_from_u64(settings.seed),
+        );
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        pool.install(|| render(&scene, &settings)).unwrap().image
+    }
+
+    #[test]
+    fn render_is_independent_of_thread_count() {
+        assert_eq!(render_on_threads(1).pixels, render_on_threads(4).pixels);
     }
 
-    main_pb.abandon_with_message("Generated.");
-    multi_pb.println("🍻 Done!!")?;
+    #[test]
+    fn non_finite_samples_are_rejected_and_counted() {
+        // A lamp of infinite radiance covering the middle of the frame.
+        let lamp = Sphere::new(
+            Vec3A::ZERO,
+            1.,
+            Arc::new(DiffuseLight::from(Vec3A::splat(f32::INFINITY))),
+        );
+        let camera = Camera::new(
+            Vec3A::new(0., 0., 5.),
+            Vec3A::ZERO,
+            Vec3A::Y,
+            40.,
+            1.,
+            0.,
+            5.,
+            (0., 1.),
+        );
+        let scene = Scene::new(lamp, HittableList::default(), Vec3A::splat(0.5), camera);
+        let settings = RenderSettings {
+            img_width: 16,
+            img_height: 16,
+            samples_per_pixel: 4,
+            ..Default::default()
+        };
 
-    Ok(())
+        let output = render(&scene, &settings).unwrap();
+        assert_eq!(output.stats.samples, 16 * 16 * 4);
+        assert!(output.stats.rejected_samples > 0);
+        assert!(output.stats.rejected_samples < output.stats.samples);
+        assert!(output.image.pixels.iter().all(|pixel| pixel.is_finite()));
+        // Corner pixels only see the background.
+        assert_eq!(output.image.pixels[0], Vec3A::splat(0.5));
+    }
 }
@@ -1,31 +1,75 @@
 pub mod dielectric;
 pub mod diffuse_light;
+pub mod henyey_greenstein;
+pub mod isotropic;
 pub mod lambertian;
 pub mod metal;
 
 use glam::Vec3A;
-use rand::distributions::Uniform;
 
-use crate::{hittable::HitRecord, ray::Ray, vec::random_vec, Rand};
+use crate::{hittable::HitRecord, ray::Ray, sampler::Sampler};
+
+pub struct ScatterRecord {
+    pub direction: Vec3A,
+    /// BSDF times cosine over the sampling density, i.e. the factor the path throughput is
+    /// multiplied by when following `direction`.
+    pub attenuation: Vec3A,
+    /// Solid angle density of `direction`; meaningless for specular lobes.
+    pub pdf: f32,
+    /// The direction comes from a delta lobe that `eval` and `pdf` cannot represent, so light
+    /// sampling is pointless for it.
+    pub specular: bool,
+}
+
+impl ScatterRecord {
+    pub fn specular(direction: Vec3A, attenuation: Vec3A) -> Self {
+        Self {
+            direction,
+            attenuation,
+            pdf: 0.,
+            specular: true,
+        }
+    }
+}
 
 pub trait Material: Sync + Send {
-    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut Rand) -> Option<(Ray, Vec3A, f32)>;
-    fn scattering_pdf(&self, _ray: &Ray, _hit: &HitRecord, _scatterd: &Ray) -> f32 {
+    /// Samples an outgoing direction for a ray arriving at `hit`.
+    fn sample(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord>;
+    /// `sample` for light of a single `wavelength` in nanometres. Only [`Self::dispersive`]
+    /// materials need to override it.
+    fn sample_wavelength(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        _wavelength: f32,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        self.sample(ray, hit, sampler)
+    }
+    /// Whether the scattered direction depends on the wavelength, so spectral paths have to
+    /// settle on one.
+    fn dispersive(&self) -> bool {
+        false
+    }
+    /// BSDF times the cosine term for scattering into `direction`, excluding specular lobes.
+    fn eval(&self, _ray: &Ray, _hit: &HitRecord, _direction: Vec3A) -> Vec3A {
+        Vec3A::ZERO
+    }
+    /// Solid angle density with which `sample` produces `direction`, excluding specular lobes.
+    fn pdf(&self, _ray: &Ray, _hit: &HitRecord, _direction: Vec3A) -> f32 {
         0.
     }
     fn emitted(&self, _ray: &Ray, _hit: &HitRecord) -> Vec3A {
         Vec3A::ZERO
     }
-}
-
-fn random_in_unit_sphere(rng: &mut Rand) -> Vec3A {
-    const UNIT: Vec3A = Vec3A::splat(1.);
-    let uniform = Uniform::new(0., 1.);
-    loop {
-        let p = 2. * random_vec(rng, uniform) - UNIT;
-        if p.length_squared() < 1.0 {
-            return p;
-        }
+    /// Surface colour at `hit` for the albedo pass.
+    fn albedo(&self, _hit: &HitRecord) -> Vec3A {
+        Vec3A::ZERO
     }
 }
 
@@ -1,40 +1,118 @@
-use super::{reflect, refract, schlick, Material};
-use crate::{hittable::HitRecord, ray::Ray, Rand};
+use super::{reflect, refract, schlick, Material, ScatterRecord};
+use crate::{hittable::HitRecord, ray::Ray, sampler::Sampler};
 use glam::Vec3A;
-use rand::Rng;
+
+/// Wavelength used for the refractive index when rendering in RGB, in nanometres.
+const RGB_WAVELENGTH: f32 = 550.;
+
+/// Index of refraction as a function of the wavelength.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RefractiveIndex {
+    Constant(f32),
+    /// `n = a + b / λ²`, with `λ` in micrometres.
+    Cauchy {
+        a: f32,
+        b: f32,
+    },
+    /// `n² = 1 + Σ b_i λ² / (λ² - c_i)`, with `λ` in micrometres and `c_i` in square
+    /// micrometres, as listed in glass catalogues.
+    Sellmeier {
+        b: [f32; 3],
+        c: [f32; 3],
+    },
+}
+
+impl RefractiveIndex {
+    pub fn at(&self, wavelength: f32) -> f32 {
+        let micrometres = wavelength / 1000.;
+        let l2 = micrometres * micrometres;
+        match self {
+            RefractiveIndex::Constant(n) => *n,
+            RefractiveIndex::Cauchy { a, b } => a + b / l2,
+            RefractiveIndex::Sellmeier { b, c } => {
+                let sum = b.iter().zip(c).map(|(b, c)| b * l2 / (l2 - c)).sum::<f32>();
+                (1. + sum).sqrt()
+            }
+        }
+    }
+}
 
 pub struct Dielectric {
-    ref_idx: f32,
+    ior: RefractiveIndex,
 }
 
 impl Dielectric {
     pub fn new(index_of_refraction: f32) -> Self {
         Self {
-            ref_idx: index_of_refraction,
+            ior: RefractiveIndex::Constant(index_of_refraction),
         }
     }
-}
 
-impl Material for Dielectric {
-    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut Rand) -> Option<(Ray, Vec3A, f32)> {
+    pub fn cauchy(a: f32, b: f32) -> Self {
+        Self {
+            ior: RefractiveIndex::Cauchy { a, b },
+        }
+    }
+
+    pub fn sellmeier(b: [f32; 3], c: [f32; 3]) -> Self {
+        Self {
+            ior: RefractiveIndex::Sellmeier { b, c },
+        }
+    }
+
+    fn scatter(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        ref_idx: f32,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
         let attenuation = Vec3A::new(1.0, 1.0, 1.0);
+        let choice = sampler.get_1d();
         let (outward_normal, ni_over_nt, cosine) = if ray.direction.dot(hit.normal) > 0. {
-            let cosine = self.ref_idx * ray.direction.dot(hit.normal) / ray.direction.length();
-            (-hit.normal, self.ref_idx, cosine)
+            let cosine = ref_idx * ray.direction.dot(hit.normal) / ray.direction.length();
+            (-hit.normal, ref_idx, cosine)
         } else {
             let cosine = -ray.direction.dot(hit.normal) / ray.direction.length();
-            (hit.normal, 1.0 / self.ref_idx, cosine)
+            (hit.normal, 1.0 / ref_idx, cosine)
         };
         if let Some(refracted) = refract(ray.direction, outward_normal, ni_over_nt) {
-            let refract_prob = schlick(cosine, self.ref_idx);
-            if rng.gen::<f32>() >= refract_prob {
-                let scattered = Ray::new(hit.point, refracted, ray.time);
-                return Some((scattered, attenuation, 0.));
+            let refract_prob = schlick(cosine, ref_idx);
+            if choice >= refract_prob {
+                return Some(ScatterRecord::specular(refracted, attenuation));
             }
         }
 
         let reflected = reflect(ray.direction, hit.normal);
-        let scattered = Ray::new(hit.point, reflected, ray.time);
-        Some((scattered, attenuation, 0.))
+        Some(ScatterRecord::specular(reflected, attenuation))
+    }
+}
+
+impl Material for Dielectric {
+    fn sample(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        self.scatter(ray, hit, self.ior.at(RGB_WAVELENGTH), sampler)
+    }
+
+    fn sample_wavelength(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        wavelength: f32,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        self.scatter(ray, hit, self.ior.at(wavelength), sampler)
+    }
+
+    fn dispersive(&self) -> bool {
+        !matches!(self.ior, RefractiveIndex::Constant(_))
+    }
+
+    fn albedo(&self, _hit: &HitRecord) -> Vec3A {
+        Vec3A::ONE
     }
 }
@@ -1,9 +1,9 @@
-use super::Material;
+use super::{Material, ScatterRecord};
 use crate::{
     hittable::HitRecord,
     ray::Ray,
+    sampler::Sampler,
     texture::{solid_color::SolidColor, Texture},
-    Rand,
 };
 use glam::Vec3A;
 
@@ -27,7 +27,7 @@ impl From<Vec3A> for DiffuseLight<SolidColor> {
 }
 
 impl<T: Texture> Material for DiffuseLight<T> {
-    fn scatter(&self, _: &Ray, _: &HitRecord, _: &mut Rand) -> Option<(Ray, Vec3A, f32)> {
+    fn sample(&self, _: &Ray, _: &HitRecord, _: &mut dyn Sampler) -> Option<ScatterRecord> {
         None
     }
 
@@ -38,4 +38,8 @@ impl<T: Texture> Material for DiffuseLight<T> {
             Vec3A::ZERO
         }
     }
+
+    fn albedo(&self, hit: &HitRecord) -> Vec3A {
+        self.emit.value(hit.u, hit.v, &hit.point).min(Vec3A::ONE)
+    }
 }
@@ -0,0 +1,86 @@
+use super::{Material, ScatterRecord};
+use crate::{
+    hittable::HitRecord,
+    onb::Onb,
+    ray::Ray,
+    sampler::Sampler,
+    texture::{solid_color::SolidColor, Texture},
+};
+use glam::Vec3A;
+use std::f32::consts::PI;
+
+/// Henyey-Greenstein phase function for use inside a medium. `g` in (-1, 1) is the mean cosine
+/// between the incoming and scattered directions: positive values scatter forward, negative
+/// values backward and 0 is isotropic.
+pub struct HenyeyGreenstein<T: Texture> {
+    /// Single-scattering albedo: the share of the collisions that scatter rather than absorb.
+    albedo: T,
+    g: f32,
+}
+
+impl<T: Texture> HenyeyGreenstein<T> {
+    pub fn new(texture: T, g: f32) -> Self {
+        Self {
+            albedo: texture,
+            g: g.clamp(-0.99, 0.99),
+        }
+    }
+
+    fn phase(&self, cos_theta: f32) -> f32 {
+        let g = self.g;
+        let denominator = 1. + g * g - 2. * g * cos_theta;
+        (1. - g * g) / (4. * PI * denominator * denominator.sqrt())
+    }
+}
+
+impl From<(Vec3A, f32)> for HenyeyGreenstein<SolidColor> {
+    fn from(value: (Vec3A, f32)) -> Self {
+        Self::new(SolidColor::new(value.0), value.1)
+    }
+}
+
+impl<T: Texture> Material for HenyeyGreenstein<T> {
+    fn sample(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        let u = sampler.get_2d();
+        let g = self.g;
+        let cos_theta = if g.abs() < 1e-3 {
+            1. - 2. * u.x
+        } else {
+            let s = (1. - g * g) / (1. - g + 2. * g * u.x);
+            ((1. + g * g - s * s) / (2. * g)).clamp(-1., 1.)
+        };
+        let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+        let phi = 2. * PI * u.y;
+
+        let uvw = Onb::build_from_w(&ray.direction);
+        let direction = uvw.local(&Vec3A::new(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        ));
+
+        Some(ScatterRecord {
+            direction,
+            attenuation: self.albedo.value(hit.u, hit.v, &hit.point),
+            pdf: self.phase(cos_theta),
+            specular: false,
+        })
+    }
+
+    fn eval(&self, ray: &Ray, hit: &HitRecord, direction: Vec3A) -> Vec3A {
+        self.albedo.value(hit.u, hit.v, &hit.point) * self.pdf(ray, hit, direction)
+    }
+
+    fn pdf(&self, ray: &Ray, _hit: &HitRecord, direction: Vec3A) -> f32 {
+        self.phase(ray.direction.normalize().dot(direction.normalize()))
+    }
+
+    fn albedo(&self, hit: &HitRecord) -> Vec3A {
+        self.albedo.value(hit.u, hit.v, &hit.point)
+    }
+}
@@ -0,0 +1,57 @@
+use super::{Material, ScatterRecord};
+use crate::{
+    hittable::HitRecord,
+    ray::Ray,
+    sampler::Sampler,
+    texture::{solid_color::SolidColor, Texture},
+    vec::uniform_sphere,
+};
+use glam::Vec3A;
+use std::f32::consts::PI;
+
+/// Phase function scattering equally in every direction, for use inside a medium.
+pub struct Isotropic<T: Texture> {
+    albedo: T,
+}
+
+impl<T: Texture> Isotropic<T> {
+    pub fn new(texture: T) -> Self {
+        Self { albedo: texture }
+    }
+}
+
+impl From<Vec3A> for Isotropic<SolidColor> {
+    fn from(value: Vec3A) -> Self {
+        Self {
+            albedo: SolidColor::new(value),
+        }
+    }
+}
+
+impl<T: Texture> Material for Isotropic<T> {
+    fn sample(
+        &self,
+        _ray: &Ray,
+        hit: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        Some(ScatterRecord {
+            direction: uniform_sphere(sampler.get_2d()),
+            attenuation: self.albedo.value(hit.u, hit.v, &hit.point),
+            pdf: 1. / (4. * PI),
+            specular: false,
+        })
+    }
+
+    fn eval(&self, _ray: &Ray, hit: &HitRecord, _direction: Vec3A) -> Vec3A {
+        self.albedo.value(hit.u, hit.v, &hit.point) / (4. * PI)
+    }
+
+    fn pdf(&self, _ray: &Ray, _hit: &HitRecord, _direction: Vec3A) -> f32 {
+        1. / (4. * PI)
+    }
+
+    fn albedo(&self, hit: &HitRecord) -> Vec3A {
+        self.albedo.value(hit.u, hit.v, &hit.point)
+    }
+}
@@ -1,11 +1,11 @@
-use super::Material;
+use super::{Material, ScatterRecord};
 use crate::{
     hittable::HitRecord,
     onb::Onb,
     ray::Ray,
+    sampler::Sampler,
     texture::{solid_color::SolidColor, Texture},
     vec::random_cosine_direction,
-    Rand,
 };
 use glam::Vec3A;
 use std::f32::consts::PI;
@@ -29,22 +29,43 @@ impl From<Vec3A> for Lambertian<SolidColor> {
 }
 
 impl<T: Texture> Material for Lambertian<T> {
-    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut Rand) -> Option<(Ray, Vec3A, f32)> {
+    fn sample(
+        &self,
+        _ray: &Ray,
+        hit: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
         let uvw = Onb::build_from_w(&hit.normal);
-        let direction = uvw.local(&random_cosine_direction(rng));
-        let scatterd = Ray::new(hit.point, direction.normalize(), ray.time);
+        let direction = uvw
+            .local(&random_cosine_direction(sampler.get_2d()))
+            .normalize();
+        let pdf = uvw.w().dot(direction) / PI;
+        if pdf <= 0. {
+            return None;
+        }
+
+        Some(ScatterRecord {
+            direction,
+            attenuation: self.albedo.value(hit.u, hit.v, &hit.point),
+            pdf,
+            specular: false,
+        })
+    }
 
-        let pdf = uvw.w().dot(scatterd.direction) / PI;
-        let albedo = self.albedo.value(hit.u, hit.v, &hit.point);
-        Some((scatterd, albedo, pdf))
+    fn eval(&self, ray: &Ray, hit: &HitRecord, direction: Vec3A) -> Vec3A {
+        self.albedo.value(hit.u, hit.v, &hit.point) * self.pdf(ray, hit, direction)
     }
 
-    fn scattering_pdf(&self, _ray: &Ray, hit: &HitRecord, scatterd: &Ray) -> f32 {
-        let cosine = hit.normal.dot(scatterd.direction.normalize());
+    fn pdf(&self, _ray: &Ray, hit: &HitRecord, direction: Vec3A) -> f32 {
+        let cosine = hit.normal.dot(direction.normalize());
         if cosine < 0.0 {
             0.
         } else {
             cosine / PI
         }
     }
+
+    fn albedo(&self, hit: &HitRecord) -> Vec3A {
+        self.albedo.value(hit.u, hit.v, &hit.point)
+    }
 }
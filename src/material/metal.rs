@@ -1,6 +1,7 @@
-use super::{random_in_unit_sphere, reflect, Material};
-use crate::{hittable::HitRecord, ray::Ray, Rand};
+use super::{reflect, Material, ScatterRecord};
+use crate::{hittable::HitRecord, onb::Onb, ray::Ray, sampler::Sampler};
 use glam::Vec3A;
+use std::f32::consts::PI;
 
 pub struct Metal {
     albedo: Vec3A,
@@ -11,23 +12,85 @@ impl Metal {
     pub fn new(albedo: Vec3A, fuzzy: f32) -> Self {
         Self {
             albedo,
-            fuzzy: fuzzy.min(1.),
+            fuzzy: fuzzy.clamp(0., 1.),
         }
     }
+
+    /// Exponent of the Phong lobe around the mirror direction: a fuzz of 1 spreads the
+    /// reflection over the whole hemisphere around it and smaller values tighten it.
+    fn exponent(&self) -> f32 {
+        2. / (self.fuzzy * self.fuzzy) - 2.
+    }
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray: &Ray, hit: &HitRecord, rng: &mut Rand) -> Option<(Ray, Vec3A, f32)> {
+    /// A perfect mirror without fuzz, a glossy lobe that light sampling can take part in
+    /// otherwise.
+    fn sample(
+        &self,
+        ray: &Ray,
+        hit: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        let reflected = reflect(ray.direction.normalize(), hit.normal);
+        if self.fuzzy == 0. {
+            return Some(ScatterRecord::specular(reflected, self.albedo));
+        }
+
+        let u = sampler.get_2d();
+        let cos_alpha = u.x.powf(1. / (self.exponent() + 1.));
+        let sin_alpha = (1. - cos_alpha * cos_alpha).max(0.).sqrt();
+        let phi = 2. * PI * u.y;
+        let direction = Onb::build_from_w(&reflected)
+            .local(&Vec3A::new(
+                sin_alpha * phi.cos(),
+                sin_alpha * phi.sin(),
+                cos_alpha,
+            ))
+            .normalize();
+        let pdf = self.pdf(ray, hit, direction);
+        if pdf <= 0. {
+            return None;
+        }
+
+        Some(ScatterRecord {
+            direction,
+            attenuation: self.eval(ray, hit, direction) / pdf,
+            pdf,
+            specular: false,
+        })
+    }
+
+    /// The lobe divided by the larger of the two cosines, which keeps the BSDF symmetric in
+    /// the two directions and never reflects more than `albedo`.
+    fn eval(&self, ray: &Ray, hit: &HitRecord, direction: Vec3A) -> Vec3A {
+        let cos_in = -ray.direction.normalize().dot(hit.normal);
+        let cos_out = direction.normalize().dot(hit.normal);
+        if cos_in <= 0. || cos_out <= 0. {
+            return Vec3A::ZERO;
+        }
+        self.albedo * self.pdf(ray, hit, direction) * cos_out / cos_in.max(cos_out)
+    }
+
+    /// Density of the Phong lobe, with the part of it below the surface absorbed.
+    fn pdf(&self, ray: &Ray, hit: &HitRecord, direction: Vec3A) -> f32 {
+        if self.fuzzy == 0. {
+            return 0.;
+        }
+        let direction = direction.normalize();
+        if direction.dot(hit.normal) <= 0. {
+            return 0.;
+        }
         let reflected = reflect(ray.direction.normalize(), hit.normal);
-        if reflected.dot(hit.normal) > 0. {
-            let scatterd = Ray::new(
-                hit.point,
-                reflected + self.fuzzy * random_in_unit_sphere(rng),
-                ray.time,
-            );
-            Some((scatterd, self.albedo, 0.))
-        } else {
-            None
+        let cos_alpha = reflected.dot(direction);
+        if cos_alpha <= 0. {
+            return 0.;
         }
+        let exponent = self.exponent();
+        (exponent + 1.) / (2. * PI) * cos_alpha.powf(exponent)
+    }
+
+    fn albedo(&self, _hit: &HitRecord) -> Vec3A {
+        self.albedo
     }
 }
@@ -0,0 +1,28 @@
+use crate::{material::Material, ray::Ray, sampler::Sampler};
+
+/// Participating medium filling the inside of a boundary, such as smoke or fog. Rays travel
+/// through it in straight lines until a free-flight sample makes them scatter.
+pub trait Medium: Sync + Send {
+    /// Parameter in `(t_min, t_max)` at which `ray` scatters inside the medium, `None` if it
+    /// makes it through.
+    fn sample_distance(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        sampler: &mut dyn Sampler,
+    ) -> Option<f32>;
+    /// Fraction of the light travelling along `ray` from `t_min` to `t_max` that is neither
+    /// absorbed nor scattered away.
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32, sampler: &mut dyn Sampler) -> f32;
+    /// Phase function of the scattering events.
+    fn phase(&self) -> &dyn Material;
+}
+
+/// Place where a ray crosses the boundary of a medium.
+#[derive(Clone, Copy)]
+pub struct MediumBoundary<'a> {
+    pub medium: &'a dyn Medium,
+    /// The ray enters the medium rather than leaving it.
+    pub entering: bool,
+}
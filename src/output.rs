@@ -0,0 +1,100 @@
+use crate::{framebuffer::Framebuffer, tonemap::DisplayTransform};
+use anyhow::{anyhow, bail, Result};
+use image::{codecs::hdr::HdrEncoder, DynamicImage, ImageBuffer, ImageFormat, Rgb};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// ASCII PPM (P3).
+    PpmAscii,
+    /// Binary PPM (P6).
+    Ppm,
+    Png8,
+    Png16,
+    /// Radiance RGBE, keeps the linear float radiance.
+    Hdr,
+    /// OpenEXR with 32-bit float channels, keeps the linear float radiance.
+    Exr,
+}
+
+impl OutputFormat {
+    /// Picks the format from the file extension. `.ppm` is written as ASCII (P3) and `.png` with
+    /// 8 bits per channel; use [`Framebuffer::save_as`] for binary PPM or 16-bit PNG.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+        Ok(match extension.as_deref() {
+            Some("ppm") => OutputFormat::PpmAscii,
+            Some("png") => OutputFormat::Png8,
+            Some("hdr") => OutputFormat::Hdr,
+            Some("exr") => OutputFormat::Exr,
+            _ => bail!("Unsupported output file: {}", path.display()),
+        })
+    }
+}
+
+impl Framebuffer {
+    /// Saves in the format matching the extension of `path`. `display` only applies to the
+    /// integer formats; HDR and EXR store the linear radiance untouched.
+    pub fn save<P: AsRef<Path>>(&self, path: P, display: &DisplayTransform) -> Result<()> {
+        let path = path.as_ref();
+        self.save_as(path, OutputFormat::from_path(path)?, display)
+    }
+
+    pub fn save_as<P: AsRef<Path>>(
+        &self,
+        path: P,
+        format: OutputFormat,
+        display: &DisplayTransform,
+    ) -> Result<()> {
+        let path = path.as_ref();
+        let (width, height) = (self.width as u32, self.height as u32);
+        let mismatch = || anyhow!("Framebuffer does not hold {}x{} pixels", width, height);
+        match format {
+            OutputFormat::PpmAscii => {
+                let mut writer = BufWriter::new(File::create(path)?);
+                self.write_ppm(&mut writer, display)?;
+                writer.flush()?;
+            }
+            OutputFormat::Ppm => {
+                let mut writer = BufWriter::new(File::create(path)?);
+                self.write_ppm_binary(&mut writer, display)?;
+                writer.flush()?;
+            }
+            OutputFormat::Png8 => {
+                let image =
+                    ImageBuffer::<Rgb<u8>, _>::from_raw(width, height, self.to_rgb8(display))
+                        .ok_or_else(mismatch)?;
+                image.save_with_format(path, ImageFormat::Png)?;
+            }
+            OutputFormat::Png16 => {
+                let image =
+                    ImageBuffer::<Rgb<u16>, _>::from_raw(width, height, self.to_rgb16(display))
+                        .ok_or_else(mismatch)?;
+                DynamicImage::ImageRgb16(image).save_with_format(path, ImageFormat::Png)?;
+            }
+            OutputFormat::Hdr => {
+                let pixels = self
+                    .pixels
+                    .iter()
+                    .map(|c| Rgb(c.to_array()))
+                    .collect::<Vec<_>>();
+                let writer = BufWriter::new(File::create(path)?);
+                HdrEncoder::new(writer).encode(&pixels, self.width, self.height)?;
+            }
+            OutputFormat::Exr => {
+                let image = ImageBuffer::<Rgb<f32>, _>::from_raw(width, height, self.to_rgb32f())
+                    .ok_or_else(mismatch)?;
+                DynamicImage::ImageRgb32F(image).save_with_format(path, ImageFormat::OpenExr)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,11 +1,10 @@
-use crate::{hittable::Hittable, onb::Onb, vec::random_cosine_direction, Rand};
+use crate::{hittable::Hittable, onb::Onb, sampler::Sampler, vec::random_cosine_direction};
 use glam::Vec3A;
-use rand::Rng;
 use std::{f32::consts::PI, sync::Arc};
 
 pub trait Pdf {
     fn value(&self, direction: Vec3A) -> f32;
-    fn generate(&self, rng: &mut Rand) -> Vec3A;
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3A;
 }
 
 pub struct CosinePdf {
@@ -30,29 +29,29 @@ impl Pdf for CosinePdf {
         }
     }
 
-    fn generate(&self, rng: &mut Rand) -> Vec3A {
-        self.uvw.local(&random_cosine_direction(rng))
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3A {
+        self.uvw.local(&random_cosine_direction(sampler.get_2d()))
     }
 }
 
-pub struct HittablePdf<H: Hittable> {
+pub struct HittablePdf<H: Hittable + ?Sized> {
     pub origin: Vec3A,
     pub hittable: Arc<H>,
 }
 
-impl<H: Hittable> HittablePdf<H> {
+impl<H: Hittable + ?Sized> HittablePdf<H> {
     pub fn new(origin: Vec3A, hittable: Arc<H>) -> Self {
         Self { origin, hittable }
     }
 }
 
-impl<H: Hittable> Pdf for HittablePdf<H> {
+impl<H: Hittable + ?Sized> Pdf for HittablePdf<H> {
     fn value(&self, direction: Vec3A) -> f32 {
         self.hittable.pdf_value(self.origin, direction)
     }
 
-    fn generate(&self, rng: &mut Rand) -> Vec3A {
-        self.hittable.random(self.origin, rng)
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3A {
+        self.hittable.random(self.origin, sampler)
     }
 }
 
@@ -71,11 +70,11 @@ impl Pdf for MixturePdf {
         0.5 * self.pdf.0.value(direction) + 0.5 * self.pdf.1.value(direction)
     }
 
-    fn generate(&self, rng: &mut Rand) -> Vec3A {
-        if rng.gen::<f32>() < 0.5 {
-            self.pdf.0.generate(rng)
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3A {
+        if sampler.get_1d() < 0.5 {
+            self.pdf.0.generate(sampler)
         } else {
-            self.pdf.1.generate(rng)
+            self.pdf.1.generate(sampler)
         }
     }
 }
@@ -0,0 +1,89 @@
+use crate::tile::Tile;
+use anyhow::Result;
+use glam::Vec3A;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::{
+    error::Error,
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+/// Receives progress updates from a render. Calls may come from any rendering thread.
+pub trait ProgressSink: Sync + Send {
+    fn start(&self, _total: u64) {}
+    fn advance(&self, _amount: u64) {}
+    /// Called with the finished row-major radiance of each tile, before it is counted by
+    /// `advance`.
+    fn tile_finished(&self, _tile: &Tile, _pixels: &[Vec3A]) {}
+    fn finish(&self) {}
+}
+
+/// Discards every update.
+pub struct SilentProgress;
+
+impl ProgressSink for SilentProgress {}
+
+/// Terminal progress bar.
+pub struct IndicatifProgress {
+    bar: ProgressBar,
+}
+
+impl IndicatifProgress {
+    pub fn new() -> Result<Self> {
+        let bar = ProgressBar::new(0);
+        bar.set_style(
+            ProgressStyle::with_template(
+                "[{elapsed_precise}] {wide_bar:.cyan/blue} {pos:>7}/{len:7} {msg}",
+            )?
+            .progress_chars("##-"),
+        );
+        Ok(Self { bar })
+    }
+}
+
+impl ProgressSink for IndicatifProgress {
+    fn start(&self, total: u64) {
+        self.bar.set_length(total);
+        self.bar.println("✨ Generating...");
+    }
+
+    fn advance(&self, amount: u64) {
+        self.bar.inc(amount);
+    }
+
+    fn finish(&self) {
+        self.bar.abandon_with_message("Generated.");
+        self.bar.println("🍻 Done!!");
+    }
+}
+
+/// Shared flag a caller can set to stop a render in progress.
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Error returned by a render that was stopped through its `CancellationToken`.
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "render cancelled")
+    }
+}
+
+impl Error for Cancelled {}
@@ -1,5 +1,6 @@
 use glam::Vec3A;
 
+#[derive(Clone, Copy)]
 pub struct Ray {
     pub origin: Vec3A,
     pub direction: Vec3A,
@@ -0,0 +1,17 @@
+use crate::Rand;
+use rand::SeedableRng;
+
+/// SplitMix64 finalizer, used to decorrelate neighbouring stream indices.
+#[inline(always)]
+pub(crate) fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Independent random stream for one sample of one pixel. The stream only depends on its
+/// indices and the global seed, so the image does not depend on how the work is scheduled.
+pub fn sample_rng(seed: u64, pixel: u64, sample: u64) -> Rand {
+    let stream = mix(mix(seed ^ mix(pixel)).wrapping_add(sample));
+    Rand::seed_from_u64(stream)
+}
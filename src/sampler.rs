@@ -0,0 +1,424 @@
+use glam::Vec2;
+use rand::Rng;
+
+use crate::{
+    rng::{mix, sample_rng},
+    Rand,
+};
+
+/// Source of the random numbers consumed while tracing one sample of one pixel. Each call hands
+/// out the next dimension of the sample, so the sequence of calls must be the same for every
+/// sample for the low-discrepancy samplers to pay off.
+pub trait Sampler {
+    /// Moves on to sample `sample` of the same pixel, starting again from its first dimension.
+    fn start_sample(&mut self, sample: u64);
+    fn get_1d(&mut self) -> f32;
+    fn get_2d(&mut self) -> Vec2;
+}
+
+/// Sample sequence used to render the image.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SamplerKind {
+    #[default]
+    Independent,
+    Stratified,
+    /// Only the first 32 dimensions, one per prime in the table, follow the Halton sequence,
+    /// which covers the camera and the first few bounces. Deeper ones are independent.
+    Halton,
+    Sobol,
+}
+
+impl SamplerKind {
+    /// Sampler for sample `sample` of pixel `pixel`, out of `samples_per_pixel` per pixel.
+    pub fn sampler(
+        &self,
+        seed: u64,
+        pixel: u64,
+        sample: u64,
+        samples_per_pixel: usize,
+    ) -> Box<dyn Sampler> {
+        match self {
+            SamplerKind::Independent => Box::new(IndependentSampler::new(seed, pixel, sample)),
+            SamplerKind::Stratified => Box::new(StratifiedSampler::new(
+                seed,
+                pixel,
+                sample,
+                samples_per_pixel,
+            )),
+            SamplerKind::Halton => Box::new(HaltonSampler::new(seed, pixel, sample)),
+            SamplerKind::Sobol => Box::new(SobolSampler::new(seed, pixel, sample)),
+        }
+    }
+}
+
+/// Uniform random numbers with no correlation between samples.
+pub struct IndependentSampler {
+    seed: u64,
+    pixel: u64,
+    rng: Rand,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64, pixel: u64, sample: u64) -> Self {
+        Self {
+            seed,
+            pixel,
+            rng: sample_rng(seed, pixel, sample),
+        }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_sample(&mut self, sample: u64) {
+        self.rng = sample_rng(self.seed, self.pixel, sample);
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        self.rng.gen()
+    }
+
+    fn get_2d(&mut self) -> Vec2 {
+        Vec2::new(self.rng.gen(), self.rng.gen())
+    }
+}
+
+/// Jittered sampling: every dimension is split into about one stratum per sample (a grid as
+/// close to square as possible in 2D) and each sample lands at a random position inside its
+/// stratum. Strata are assigned through a per-pixel, per-dimension permutation so the dimensions
+/// stay uncorrelated.
+pub struct StratifiedSampler {
+    seed: u64,
+    pixel: u64,
+    pixel_seed: u64,
+    sample: u64,
+    dimension: u64,
+    x_strata: u32,
+    y_strata: u32,
+    rng: Rand,
+}
+
+impl StratifiedSampler {
+    pub fn new(seed: u64, pixel: u64, sample: u64, samples_per_pixel: usize) -> Self {
+        let (x_strata, y_strata) = Self::strata(samples_per_pixel);
+        Self {
+            seed,
+            pixel,
+            pixel_seed: mix(seed ^ mix(pixel)),
+            sample,
+            dimension: 0,
+            x_strata,
+            y_strata,
+            rng: sample_rng(seed, pixel, sample),
+        }
+    }
+
+    /// Grid of 2D strata for `samples_per_pixel` samples. Counts without a divisor close to
+    /// their square root get the smallest square-ish grid holding them, leaving a few strata
+    /// empty instead of collapsing to a single row.
+    pub fn strata(samples_per_pixel: usize) -> (u32, u32) {
+        let count = samples_per_pixel.clamp(1, u32::MAX as usize) as u32;
+        let root = (count as f32).sqrt();
+        let mut x_strata = root as u32;
+        while !count.is_multiple_of(x_strata) {
+            x_strata -= 1;
+        }
+        if (x_strata as f32) < root / 2. {
+            x_strata = root.ceil() as u32;
+        }
+        (x_strata, count.div_ceil(x_strata))
+    }
+
+    fn stratum(&mut self) -> u32 {
+        let count = self.x_strata * self.y_strata;
+        let index = (self.sample % count as u64) as u32;
+        let pattern = mix(self.pixel_seed.wrapping_add(self.dimension)) as u32;
+        self.dimension += 1;
+        permute(index, count, pattern)
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_sample(&mut self, sample: u64) {
+        self.sample = sample;
+        self.dimension = 0;
+        self.rng = sample_rng(self.seed, self.pixel, sample);
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let count = self.x_strata * self.y_strata;
+        let stratum = self.stratum();
+        ((stratum as f32 + self.rng.gen::<f32>()) / count as f32).min(ONE_MINUS_EPSILON)
+    }
+
+    fn get_2d(&mut self) -> Vec2 {
+        let stratum = self.stratum();
+        let x = (stratum % self.x_strata) as f32 + self.rng.gen::<f32>();
+        let y = (stratum / self.x_strata) as f32 + self.rng.gen::<f32>();
+        Vec2::new(
+            (x / self.x_strata as f32).min(ONE_MINUS_EPSILON),
+            (y / self.y_strata as f32).min(ONE_MINUS_EPSILON),
+        )
+    }
+}
+
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+/// Halton sequence over the sample index, one prime base per dimension, with a per-pixel
+/// Cranley-Patterson rotation so neighbouring pixels do not share the same points. Dimensions
+/// past the prime table fall back to independent numbers.
+pub struct HaltonSampler {
+    seed: u64,
+    pixel: u64,
+    pixel_seed: u64,
+    sample: u64,
+    dimension: usize,
+    rng: Rand,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64, pixel: u64, sample: u64) -> Self {
+        Self {
+            seed,
+            pixel,
+            pixel_seed: mix(seed ^ mix(pixel)),
+            sample,
+            dimension: 0,
+            rng: sample_rng(seed, pixel, sample),
+        }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_sample(&mut self, sample: u64) {
+        self.sample = sample;
+        self.dimension = 0;
+        self.rng = sample_rng(self.seed, self.pixel, sample);
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let Some(&base) = PRIMES.get(self.dimension) else {
+            return self.rng.gen();
+        };
+        let offset = to_unit(mix(self.pixel_seed.wrapping_add(self.dimension as u64)) as u32);
+        self.dimension += 1;
+
+        let value = radical_inverse(base, self.sample) + offset;
+        (value - value.floor()).min(ONE_MINUS_EPSILON)
+    }
+
+    fn get_2d(&mut self) -> Vec2 {
+        Vec2::new(self.get_1d(), self.get_1d())
+    }
+}
+
+/// Sobol (0,2)-sequence with Owen scrambling, after Burley's "Practical Hash-based Owen
+/// Scrambling". Every dimension pair reuses the first two Sobol dimensions with its own
+/// shuffle of the sample index and its own scramble, which keeps the 2D projections well
+/// stratified without direction number tables.
+pub struct SobolSampler {
+    pixel_seed: u64,
+    sample: u32,
+    dimension: u64,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64, pixel: u64, sample: u64) -> Self {
+        Self {
+            pixel_seed: mix(seed ^ mix(pixel)),
+            sample: sample as u32,
+            dimension: 0,
+        }
+    }
+
+    fn seeds(&mut self) -> (u32, u32, u32) {
+        let hash = mix(self.pixel_seed.wrapping_add(self.dimension));
+        self.dimension += 1;
+        (hash as u32, (hash >> 32) as u32, mix(hash) as u32)
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_sample(&mut self, sample: u64) {
+        self.sample = sample as u32;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let (shuffle, scramble, _) = self.seeds();
+        let index = nested_uniform_scramble(self.sample, shuffle);
+        to_unit(nested_uniform_scramble(index.reverse_bits(), scramble))
+    }
+
+    fn get_2d(&mut self) -> Vec2 {
+        let (shuffle, scramble_x, scramble_y) = self.seeds();
+        let index = nested_uniform_scramble(self.sample, shuffle);
+        Vec2::new(
+            to_unit(nested_uniform_scramble(index.reverse_bits(), scramble_x)),
+            to_unit(nested_uniform_scramble(sobol_second(index), scramble_y)),
+        )
+    }
+}
+
+const ONE_MINUS_EPSILON: f32 = 1. - f32::EPSILON / 2.;
+
+/// Maps the high 24 bits of `x` onto [0, 1).
+fn to_unit(x: u32) -> f32 {
+    (x >> 8) as f32 * (1. / (1u32 << 24) as f32)
+}
+
+fn radical_inverse(base: u32, mut index: u64) -> f32 {
+    let base = base as u64;
+    let inv_base = 1. / base as f64;
+    let mut reversed = 0u64;
+    let mut inv_base_n = 1.;
+    while index > 0 {
+        let next = index / base;
+        reversed = reversed * base + (index - next * base);
+        inv_base_n *= inv_base;
+        index = next;
+    }
+    ((reversed as f64 * inv_base_n) as f32).min(ONE_MINUS_EPSILON)
+}
+
+/// Second Sobol dimension, generated by the primitive polynomial x + 1.
+fn sobol_second(mut index: u32) -> u32 {
+    let mut v = 1u32 << 31;
+    let mut result = 0;
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= v;
+        }
+        index >>= 1;
+        v ^= v >> 1;
+    }
+    result
+}
+
+/// Laine-Karras style hash that only lets bits influence higher bits.
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x
+}
+
+/// Owen scrambling of a 32-bit fixed point value.
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+/// Element `i` of a pseudo-random permutation of `0..l` selected by `p`, from Kensler's
+/// "Correlated Multi-Jittered Sampling".
+fn permute(mut i: u32, l: u32, p: u32) -> u32 {
+    let mut w = l - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < l {
+            break;
+        }
+    }
+    i.wrapping_add(p) % l
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: [SamplerKind; 4] = [
+        SamplerKind::Independent,
+        SamplerKind::Stratified,
+        SamplerKind::Halton,
+        SamplerKind::Sobol,
+    ];
+
+    #[test]
+    fn low_discrepancy_values_are_in_unit_interval() {
+        for kind in [SamplerKind::Halton, SamplerKind::Sobol] {
+            for pixel in 0..8 {
+                let mut sampler = kind.sampler(7, pixel, 0, 64);
+                for sample in 0..64 {
+                    sampler.start_sample(sample);
+                    // Past the prime table too.
+                    for _ in 0..40 {
+                        let value = sampler.get_1d();
+                        assert!((0. ..1.).contains(&value), "{:?} {}", kind, value);
+                        let value = sampler.get_2d();
+                        assert!(value.cmpge(Vec2::ZERO).all() && value.cmplt(Vec2::ONE).all());
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stratified_samples_cover_every_stratum() {
+        for samples_per_pixel in [16, 12, 7] {
+            let (x_strata, y_strata) = StratifiedSampler::strata(samples_per_pixel);
+            let count = x_strata * y_strata;
+            let mut sampler = SamplerKind::Stratified.sampler(3, 5, 0, samples_per_pixel);
+            let mut strata_1d = vec![false; count as usize];
+            let mut strata_2d = vec![false; count as usize];
+            for sample in 0..count as u64 {
+                sampler.start_sample(sample);
+                let value = sampler.get_1d();
+                strata_1d[(value * count as f32) as usize] = true;
+                let value = sampler.get_2d();
+                let x = (value.x * x_strata as f32) as u32;
+                let y = (value.y * y_strata as f32) as u32;
+                strata_2d[(y * x_strata + x) as usize] = true;
+            }
+            assert!(strata_1d.iter().all(|&hit| hit));
+            assert!(strata_2d.iter().all(|&hit| hit));
+        }
+    }
+
+    #[test]
+    fn sequence_is_deterministic_per_pixel_and_seed() {
+        let values = |sampler: &mut dyn Sampler| {
+            (0..8)
+                .flat_map(|_| {
+                    let value = sampler.get_2d();
+                    [sampler.get_1d(), value.x, value.y]
+                })
+                .collect::<Vec<_>>()
+        };
+        for kind in KINDS {
+            let mut reused = kind.sampler(11, 42, 0, 16);
+            for sample in 0..16 {
+                let fresh = values(kind.sampler(11, 42, sample, 16).as_mut());
+                assert_eq!(fresh, values(kind.sampler(11, 42, sample, 16).as_mut()));
+                reused.start_sample(sample);
+                assert_eq!(fresh, values(reused.as_mut()), "{:?}", kind);
+                assert_ne!(fresh, values(kind.sampler(12, 42, sample, 16).as_mut()));
+                assert_ne!(fresh, values(kind.sampler(11, 43, sample, 16).as_mut()));
+            }
+        }
+    }
+}
@@ -1,8 +1,11 @@
 use std::sync::Arc;
 
 use crate::{
+    camera::Camera,
     hittable::{
         boxtype::BoxType,
+        bvh::BvhTree,
+        constant_medium::ConstantMedium,
         flip_face::FlipFace,
         hittable_list::HittableList,
         moving_sphere::MovingSphere,
@@ -10,9 +13,11 @@ use crate::{
         rotate::{Axis, Rotate},
         sphere::Sphere,
         translate::Translate,
+        Hittable,
     },
     material::{
-        dielectric::Dielectric, diffuse_light::DiffuseLight, lambertian::Lambertian, metal::Metal,
+        dielectric::Dielectric, diffuse_light::DiffuseLight, isotropic::Isotropic,
+        lambertian::Lambertian, metal::Metal,
     },
     texture::checker_texture::CheckerTexture,
     vec::random_vec,
@@ -21,8 +26,51 @@ use crate::{
 use glam::Vec3A;
 use rand::{distributions::Uniform, Rng};
 
-#[allow(dead_code)]
-pub fn random_scene(rng: &mut Rand) -> HittableList {
+pub struct Scene {
+    pub world: Box<dyn Hittable>,
+    pub lights: Arc<dyn Hittable>,
+    pub background: Vec3A,
+    pub camera: Camera,
+}
+
+impl Scene {
+    pub fn new<W: Hittable + 'static, L: Hittable + 'static>(
+        world: W,
+        lights: L,
+        background: Vec3A,
+        camera: Camera,
+    ) -> Self {
+        Self {
+            world: Box::new(world),
+            lights: Arc::new(lights),
+            background,
+            camera,
+        }
+    }
+
+    /// Hash of the bounds of the world and the lights, the background and the camera, which
+    /// tells checkpoints of different scenes apart. Scenes that only differ in materials or
+    /// inside the same bounds look the same to it.
+    pub fn fingerprint(&self) -> u64 {
+        let bounds = |hittable: &dyn Hittable| match hittable.bounding_box((0., 1.)) {
+            Some(aabb) => [aabb.min.to_array(), aabb.max.to_array()],
+            None => [[f32::NAN; 3]; 2],
+        };
+        // FNV-1a, which unlike `DefaultHasher` is the same in every build.
+        bounds(self.world.as_ref())
+            .into_iter()
+            .chain(bounds(self.lights.as_ref()))
+            .flatten()
+            .chain(self.background.to_array())
+            .chain(self.camera.parameters())
+            .flat_map(|value| value.to_bits().to_le_bytes())
+            .fold(0xcbf29ce484222325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            })
+    }
+}
+
+pub fn random_scene(aspect_ratio: f32, rng: &mut Rand) -> Scene {
     let mut world = HittableList::default();
 
     let checker_tex = CheckerTexture::from((Vec3A::new(0.2, 0.3, 0.1), Vec3A::splat(0.9)));
@@ -77,21 +125,74 @@ pub fn random_scene(rng: &mut Rand) -> HittableList {
     let mat3 = Metal::new(Vec3A::new(0.7, 0.6, 0.5), 0.0).into();
     world.push(Sphere::new(Vec3A::new(4.0, 1.0, 0.0), 1.0, mat3));
 
-    world
+    let look_from = Vec3A::new(13., 2., 3.);
+    let look_at = Vec3A::ZERO;
+    let camera = Camera::new(
+        look_from,
+        look_at,
+        Vec3A::new(0.0, 1.0, 0.0),
+        20.0,
+        aspect_ratio,
+        0.1,
+        10.0,
+        (0., 1.),
+    );
+
+    Scene::new(
+        BvhTree::new(world.with_object_ids().objects, (0., 1.), rng),
+        HittableList::default(),
+        Vec3A::new(0.7, 0.8, 1.0),
+        camera,
+    )
+}
+
+pub fn cornell_box(aspect_ratio: f32, rng: &mut Rand) -> Scene {
+    cornell_room(
+        aspect_ratio,
+        rng,
+        ((213., 343.), (227., 332.)),
+        15.,
+        |_, instance| instance,
+    )
+}
+
+/// The Cornell box with its two blocks replaced by black and white smoke, lit by a large light.
+pub fn cornell_smoke(aspect_ratio: f32, rng: &mut Rand) -> Scene {
+    cornell_room(
+        aspect_ratio,
+        rng,
+        ((113., 443.), (127., 432.)),
+        7.,
+        |index, instance| {
+            let albedo = if index == 0 { Vec3A::ZERO } else { Vec3A::ONE };
+            ConstantMedium::new(Arc::new(instance), 0.01, Arc::new(Isotropic::from(albedo)))
+        },
+    )
 }
 
-pub fn cornell_box() -> HittableList {
+/// Walls, ceiling light and camera of the Cornell box. The light covers `light_extent` in x and
+/// z with radiance `light_strength`, and each of the two blocks goes through `block`, along with
+/// its index, before it is added.
+fn cornell_room<H: Hittable + 'static>(
+    aspect_ratio: f32,
+    rng: &mut Rand,
+    light_extent: ((f32, f32), (f32, f32)),
+    light_strength: f32,
+    block: impl Fn(usize, Translate<Rotate<BoxType>>) -> H,
+) -> Scene {
     let mut world = HittableList::default();
 
     let red = Arc::new(Lambertian::from(Vec3A::new(0.65, 0.05, 0.05)));
     let white = Arc::new(Lambertian::from(Vec3A::new(0.73, 0.73, 0.73)));
     let green = Arc::new(Lambertian::from(Vec3A::new(0.12, 0.45, 0.15)));
-    let light = Arc::new(DiffuseLight::from(Vec3A::new(15., 15., 15.)));
+    let light = Arc::new(DiffuseLight::from(Vec3A::splat(light_strength)));
 
     world.push(AARect::new(Plane::YZ, (0., 555.), (0., 555.), 555., green));
     world.push(AARect::new(Plane::YZ, (0., 555.), (0., 555.), 0., red));
 
-    let light_rect = AARect::new(Plane::XZ, (213., 343.), (227., 332.), 554., light).into();
+    let (light_x, light_z) = light_extent;
+    let lights = AARect::new(Plane::XZ, light_x, light_z, 554., light.clone());
+    let light_rect = AARect::new(Plane::XZ, light_x, light_z, 554., light).into();
     world.push(FlipFace::new(light_rect));
     world.push(AARect::new(
         Plane::XZ,
@@ -120,15 +221,35 @@ pub fn cornell_box() -> HittableList {
         let instance = Rotate::new(Axis::Y, Arc::new(instance), 15.);
         let instance = Translate::new(Arc::new(instance), Vec3A::new(265., 0., 295.));
 
-        instance
+        block(0, instance)
     });
     world.push({
         let instance = BoxType::new((Vec3A::ZERO, Vec3A::new(165., 165., 165.)), white);
         let instance = Rotate::new(Axis::Y, Arc::new(instance), -18.);
         let instance = Translate::new(Arc::new(instance), Vec3A::new(130., 0., 65.));
 
-        instance
+        block(1, instance)
     });
 
-    world
+    let look_from = Vec3A::new(278., 278., -800.);
+    let look_at = Vec3A::new(278., 278., 0.);
+    let focus_dist = (look_from - look_at).length();
+    let aperture = 0.;
+    let camera = Camera::new(
+        look_from,
+        look_at,
+        Vec3A::new(0.0, 1.0, 0.0),
+        40.0,
+        aspect_ratio,
+        aperture,
+        focus_dist,
+        (0., 1.),
+    );
+
+    Scene::new(
+        BvhTree::new(world.with_object_ids().objects, (0., 1.), rng),
+        lights,
+        Vec3A::ZERO,
+        camera,
+    )
 }
@@ -0,0 +1,156 @@
+use glam::{Vec3A, Vec4};
+use std::sync::OnceLock;
+
+/// Shortest wavelength traced in spectral mode, in nanometres.
+pub const MIN_WAVELENGTH: f32 = 380.;
+/// Longest wavelength traced in spectral mode, in nanometres.
+pub const MAX_WAVELENGTH: f32 = 780.;
+
+const RANGE: f32 = MAX_WAVELENGTH - MIN_WAVELENGTH;
+
+/// Four wavelengths carried by one spectral path: a uniformly drawn hero wavelength and three
+/// more spread evenly across the visible range, after Wilkie et al.'s "Hero Wavelength Spectral
+/// Sampling".
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SampledWavelengths {
+    pub lambda: [f32; 4],
+    terminated: bool,
+}
+
+impl SampledWavelengths {
+    pub fn sample(u: f32) -> Self {
+        let hero = u * RANGE;
+        let lambda =
+            [0., 1., 2., 3.].map(|i| MIN_WAVELENGTH + (hero + i * RANGE / 4.).rem_euclid(RANGE));
+        Self {
+            lambda,
+            terminated: false,
+        }
+    }
+
+    pub fn hero(&self) -> f32 {
+        self.lambda[0]
+    }
+
+    /// Drops the secondary wavelengths once the path hits something whose direction depends on
+    /// the wavelength. Returns the factor the throughput has to be scaled by, which moves their
+    /// share of the estimate onto the hero.
+    pub fn terminate_secondary(&mut self) -> Vec4 {
+        if self.terminated {
+            Vec4::X
+        } else {
+            self.terminated = true;
+            Vec4::new(4., 0., 0., 0.)
+        }
+    }
+
+    /// Values of a smooth spectrum matching the linear sRGB colour `rgb` at the four
+    /// wavelengths, using Smits' "An RGB-to-Spectrum Conversion for Reflectances".
+    pub fn upsample(&self, rgb: Vec3A) -> Vec4 {
+        Vec4::from_array(self.lambda.map(|lambda| smits(rgb, bin(lambda))))
+    }
+
+    /// Linear sRGB colour of the spectral radiance estimate `radiance` at the four wavelengths.
+    pub fn to_rgb(&self, radiance: Vec4) -> Vec3A {
+        let mut xyz = Vec3A::ZERO;
+        for (lambda, value) in self.lambda.iter().zip(radiance.to_array()) {
+            // Each wavelength is uniformly distributed over the range on its own.
+            xyz += value * cie_xyz(*lambda) * RANGE / 4.;
+        }
+        xyz_to_srgb(xyz / cie_integrals())
+    }
+}
+
+/// CIE 1931 colour matching functions, using the multi-lobe Gaussian fit from Wyman, Sloan and
+/// Shirley's "Simple Analytic Approximations to the CIE XYZ Color Matching Functions".
+fn cie_xyz(lambda: f32) -> Vec3A {
+    let g = |mu: f32, sigma_low: f32, sigma_high: f32| {
+        let sigma = if lambda < mu { sigma_low } else { sigma_high };
+        let t = (lambda - mu) / sigma;
+        (-0.5 * t * t).exp()
+    };
+    Vec3A::new(
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    )
+}
+
+/// Integrals of the colour matching functions over the traced range, so that a constant
+/// spectrum of 1 maps to XYZ (1, 1, 1).
+fn cie_integrals() -> Vec3A {
+    static INTEGRALS: OnceLock<Vec3A> = OnceLock::new();
+    *INTEGRALS.get_or_init(|| {
+        let steps = 4 * RANGE as usize;
+        let step = RANGE / steps as f32;
+        (0..steps)
+            .map(|i| cie_xyz(MIN_WAVELENGTH + (i as f32 + 0.5) * step) * step)
+            .sum()
+    })
+}
+
+/// Linear sRGB from XYZ normalized to an equal-energy white, adapted to D65 by scaling XYZ.
+fn xyz_to_srgb(xyz: Vec3A) -> Vec3A {
+    let xyz = xyz * Vec3A::new(0.95047, 1., 1.08883);
+    Vec3A::new(
+        3.2404542 * xyz.x - 1.5371385 * xyz.y - 0.4985314 * xyz.z,
+        -0.969266 * xyz.x + 1.8760108 * xyz.y + 0.0415560 * xyz.z,
+        0.0556434 * xyz.x - 0.2040259 * xyz.y + 1.0572252 * xyz.z,
+    )
+}
+
+const SMITS_START: f32 = 380.;
+const SMITS_END: f32 = 720.;
+const SMITS_WHITE: [f32; 10] = [
+    1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000,
+];
+const SMITS_CYAN: [f32; 10] = [
+    0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000,
+];
+const SMITS_MAGENTA: [f32; 10] = [
+    1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959,
+];
+const SMITS_YELLOW: [f32; 10] = [
+    0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840,
+];
+const SMITS_RED: [f32; 10] = [
+    0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149,
+];
+const SMITS_GREEN: [f32; 10] = [
+    0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025,
+];
+const SMITS_BLUE: [f32; 10] = [
+    1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496,
+];
+
+/// Index of the Smits bin containing `lambda`; the outer bins extend past the table.
+fn bin(lambda: f32) -> usize {
+    let t = (lambda - SMITS_START) / (SMITS_END - SMITS_START);
+    ((t * 10.) as usize).min(9)
+}
+
+fn smits(rgb: Vec3A, bin: usize) -> f32 {
+    let (r, g, b) = (rgb.x, rgb.y, rgb.z);
+    if r <= g && r <= b {
+        r * SMITS_WHITE[bin]
+            + if g <= b {
+                (g - r) * SMITS_CYAN[bin] + (b - g) * SMITS_BLUE[bin]
+            } else {
+                (b - r) * SMITS_CYAN[bin] + (g - b) * SMITS_GREEN[bin]
+            }
+    } else if g <= r && g <= b {
+        g * SMITS_WHITE[bin]
+            + if r <= b {
+                (r - g) * SMITS_MAGENTA[bin] + (b - r) * SMITS_BLUE[bin]
+            } else {
+                (b - g) * SMITS_MAGENTA[bin] + (r - b) * SMITS_RED[bin]
+            }
+    } else {
+        b * SMITS_WHITE[bin]
+            + if r <= g {
+                (r - b) * SMITS_YELLOW[bin] + (g - r) * SMITS_GREEN[bin]
+            } else {
+                (g - b) * SMITS_YELLOW[bin] + (r - g) * SMITS_RED[bin]
+            }
+    }
+}
@@ -0,0 +1,55 @@
+/// Rectangular block of pixels rendered as one unit of work, row 0 being the top of the image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tile {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Tile {
+    /// Pixel coordinates covered by the tile, in row-major order.
+    pub fn pixels(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (self.y..self.y + self.height)
+            .flat_map(move |y| (self.x..self.x + self.width).map(move |x| (x, y)))
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+
+    pub fn overlaps(&self, other: &Tile) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+
+    /// The tile grown by `margin` pixels on every side, clipped to a `width` x `height` image.
+    pub fn expand(&self, margin: usize, width: usize, height: usize) -> Tile {
+        let x = self.x.saturating_sub(margin);
+        let y = self.y.saturating_sub(margin);
+        Tile {
+            x,
+            y,
+            width: (self.x + self.width + margin).min(width) - x,
+            height: (self.y + self.height + margin).min(height) - y,
+        }
+    }
+}
+
+/// Splits an image into square tiles of `size` pixels, clipped at the right and bottom edges.
+pub fn tiles(img_width: usize, img_height: usize, size: usize) -> Vec<Tile> {
+    let size = size.max(1);
+    (0..img_height)
+        .step_by(size)
+        .flat_map(|y| {
+            (0..img_width).step_by(size).map(move |x| Tile {
+                x,
+                y,
+                width: size.min(img_width - x),
+                height: size.min(img_height - y),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,191 @@
+use crate::luminance;
+use glam::{Mat3A, Vec3A};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ToneMapper {
+    /// Clips everything above 1.
+    #[default]
+    Linear,
+    /// Reinhard on the luminance, `L / (1 + L)`.
+    Reinhard,
+    /// Reinhard on the luminance that maps `white` to 1 instead of infinity.
+    ExtendedReinhard { white: f32 },
+    /// Stephen Hill's fit of the ACES reference rendering and output transforms.
+    Aces,
+    /// Minimal AgX with the default contrast curve.
+    AgX,
+}
+
+impl ToneMapper {
+    /// Maps linear scene radiance to linear display values in `[0, 1]`.
+    pub fn apply(&self, color: Vec3A) -> Vec3A {
+        let color = color.max(Vec3A::ZERO);
+        let mapped = match *self {
+            ToneMapper::Linear => color,
+            ToneMapper::Reinhard => scale_luminance(color, |l| l / (1. + l)),
+            ToneMapper::ExtendedReinhard { white } => {
+                scale_luminance(color, |l| l * (1. + l / (white * white)) / (1. + l))
+            }
+            ToneMapper::Aces => aces(color),
+            ToneMapper::AgX => agx(color),
+        };
+        mapped.clamp(Vec3A::ZERO, Vec3A::ONE)
+    }
+}
+
+fn scale_luminance(color: Vec3A, curve: impl Fn(f32) -> f32) -> Vec3A {
+    let l = luminance(color);
+    if l > 0. {
+        color * (curve(l) / l)
+    } else {
+        Vec3A::ZERO
+    }
+}
+
+fn aces(color: Vec3A) -> Vec3A {
+    const INPUT: Mat3A = Mat3A::from_cols_array(&[
+        0.59719, 0.07600, 0.02840, //
+        0.35458, 0.90834, 0.13383, //
+        0.04823, 0.01566, 0.83777,
+    ]);
+    const OUTPUT: Mat3A = Mat3A::from_cols_array(&[
+        1.60475, -0.10208, -0.00327, //
+        -0.53108, 1.10813, -0.07276, //
+        -0.07367, -0.00605, 1.07602,
+    ]);
+
+    let v = INPUT * color;
+    let a = v * (v + 0.0245786) - 0.000090537;
+    let b = v * (0.983729 * v + 0.432951) + 0.238081;
+    OUTPUT * (a / b)
+}
+
+fn agx(color: Vec3A) -> Vec3A {
+    #[rustfmt::skip]
+    const INSET: Mat3A = Mat3A::from_cols_array(&[
+        0.84247905, 0.042328242, 0.042375654,
+        0.0784336, 0.87846863, 0.0784336,
+        0.079223745, 0.07916613, 0.879143,
+    ]);
+    #[rustfmt::skip]
+    const OUTSET: Mat3A = Mat3A::from_cols_array(&[
+        1.196879, -0.052896854, -0.052971635,
+        -0.09802088, 1.1519032, -0.09804345,
+        -0.09902974, -0.098961174, 1.1510737,
+    ]);
+    const MIN_EV: f32 = -12.47393;
+    const MAX_EV: f32 = 4.026069;
+
+    let v = INSET * color;
+    let v = Vec3A::from_array(v.to_array().map(|c| c.max(1e-10).log2()));
+    let x = ((v - MIN_EV) / (MAX_EV - MIN_EV)).clamp(Vec3A::ZERO, Vec3A::ONE);
+
+    // Polynomial fit of the default AgX contrast curve.
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    let v =
+        15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x
+            - 0.00232;
+
+    // The curve produces display-encoded values; bring them back to linear.
+    let v = (OUTSET * v).max(Vec3A::ZERO);
+    Vec3A::from_array(v.to_array().map(|c| c.powf(2.2)))
+}
+
+/// sRGB transfer function, from linear `[0, 1]` to encoded `[0, 1]`.
+pub fn srgb_encode(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1. / 2.4) - 0.055
+    }
+}
+
+/// Turns linear radiance into encoded display values for the 8 and 16-bit outputs.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DisplayTransform {
+    /// Exposure adjustment in stops; every stop doubles the radiance.
+    pub exposure: f32,
+    pub tone_mapper: ToneMapper,
+}
+
+impl DisplayTransform {
+    pub fn new(exposure: f32, tone_mapper: ToneMapper) -> Self {
+        Self {
+            exposure,
+            tone_mapper,
+        }
+    }
+
+    pub fn apply(&self, color: Vec3A) -> Vec3A {
+        let exposed = color * self.exposure.exp2();
+        let mapped = self.tone_mapper.apply(exposed);
+        Vec3A::from_array(mapped.to_array().map(srgb_encode))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAPPERS: [ToneMapper; 5] = [
+        ToneMapper::Linear,
+        ToneMapper::Reinhard,
+        ToneMapper::ExtendedReinhard { white: 4. },
+        ToneMapper::Aces,
+        ToneMapper::AgX,
+    ];
+
+    #[test]
+    fn luminance_curves_match_their_formulas() {
+        // Grey has the same luminance as each of its channels.
+        for l in [0.1, 0.5, 1., 3., 10.] {
+            let grey = Vec3A::splat(l);
+            assert_eq!(ToneMapper::Linear.apply(grey), Vec3A::splat(l.min(1.)));
+            let reinhard = ToneMapper::Reinhard.apply(grey);
+            assert!((reinhard - Vec3A::splat(l / (1. + l))).abs().max_element() < 1e-5);
+        }
+        let white = ToneMapper::ExtendedReinhard { white: 4. }.apply(Vec3A::splat(4.));
+        assert!((white - Vec3A::ONE).abs().max_element() < 1e-5);
+    }
+
+    #[test]
+    fn curves_are_monotonic_and_bounded() {
+        for mapper in MAPPERS {
+            let mut previous = 0.;
+            for i in 0..=200 {
+                let l = i as f32 * 0.1;
+                let mapped = mapper.apply(Vec3A::splat(l));
+                assert!(
+                    mapped.cmpge(Vec3A::ZERO).all() && mapped.cmple(Vec3A::ONE).all(),
+                    "{:?} maps {} to {}",
+                    mapper,
+                    l,
+                    mapped
+                );
+                let value = luminance(mapped);
+                assert!(value >= previous - 1e-5, "{:?} falls at {}", mapper, l);
+                previous = value;
+            }
+            assert!(luminance(mapper.apply(Vec3A::ZERO)) < 1e-3);
+        }
+    }
+
+    #[test]
+    fn srgb_encoding_follows_the_standard_curve() {
+        assert_eq!(srgb_encode(0.), 0.);
+        assert!((srgb_encode(1.) - 1.).abs() < 1e-6);
+        assert!((srgb_encode(0.5) - 0.735357).abs() < 1e-5);
+        assert!((srgb_encode(0.18) - 0.461356).abs() < 1e-5);
+        // The linear and power segments meet.
+        let knee = 0.0031308;
+        assert!((srgb_encode(knee) - srgb_encode(knee + 1e-7)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn every_stop_doubles_the_exposure() {
+        let color = Vec3A::new(0.1, 0.2, 0.3);
+        let brighter = DisplayTransform::new(1., ToneMapper::Linear).apply(color);
+        assert_eq!(brighter, DisplayTransform::default().apply(color * 2.));
+    }
+}
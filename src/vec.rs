@@ -1,10 +1,8 @@
 use std::f32::consts::PI;
 
-use glam::Vec3A;
+use glam::{Vec2, Vec3A};
 use rand::{distributions::Uniform, prelude::Distribution, Rng};
 
-use crate::Rand;
-
 #[inline(always)]
 pub fn random_vec<R: Rng>(rng: &mut R, uniform: Uniform<f32>) -> Vec3A {
     Vec3A::new(
@@ -15,9 +13,8 @@ pub fn random_vec<R: Rng>(rng: &mut R, uniform: Uniform<f32>) -> Vec3A {
 }
 
 #[inline(always)]
-pub fn random_cosine_direction(rng: &mut Rand) -> Vec3A {
-    let r1 = rng.gen::<f32>();
-    let r2 = rng.gen::<f32>();
+pub fn random_cosine_direction(u: Vec2) -> Vec3A {
+    let (r1, r2) = (u.x, u.y);
     let z = (1. - r2).sqrt();
 
     let phi = 2. * PI * r1;
@@ -27,3 +24,28 @@ pub fn random_cosine_direction(rng: &mut Rand) -> Vec3A {
 
     Vec3A::new(x, y, z)
 }
+
+/// Uniform direction, i.e. a point on the surface of the unit sphere.
+#[inline(always)]
+pub fn uniform_sphere(u: Vec2) -> Vec3A {
+    let z = 1. - 2. * u.x;
+    let r = (1. - z * z).max(0.).sqrt();
+    let phi = 2. * PI * u.y;
+    Vec3A::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Uniform point inside the unit disk in the xy plane, using Shirley's concentric mapping so
+/// stratified samples stay stratified.
+#[inline(always)]
+pub fn random_in_unit_disk(u: Vec2) -> Vec3A {
+    let offset = 2. * u - Vec2::ONE;
+    if offset == Vec2::ZERO {
+        return Vec3A::ZERO;
+    }
+    let (r, theta) = if offset.x.abs() > offset.y.abs() {
+        (offset.x, PI / 4. * (offset.y / offset.x))
+    } else {
+        (offset.y, PI / 2. - PI / 4. * (offset.x / offset.y))
+    };
+    Vec3A::new(r * theta.cos(), r * theta.sin(), 0.)
+}